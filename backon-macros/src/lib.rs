@@ -0,0 +1,317 @@
+//! Attribute macros for [backon](https://docs.rs/backon).
+//!
+//! Please use them through backon with the `macros` feature enabled instead of depending on this crate directly.
+
+#![deny(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::Ident;
+use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::TokenTree;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::visit_mut::VisitMut;
+use syn::Block;
+use syn::Expr;
+use syn::ExprPath;
+use syn::FnArg;
+use syn::Item;
+use syn::ItemFn;
+use syn::Macro;
+use syn::MetaNameValue;
+use syn::ReturnType;
+use syn::Token;
+
+/// Retry the body of a function with backoff.
+///
+/// The function must return a `Result`. Async functions are rewritten into a `Retry`, and the other
+/// functions into a `BlockingRetry`, so the body will be executed again for every attempt.
+///
+/// # Arguments
+///
+/// - `backoff`: the backoff builder, required.
+/// - `when`: the condition for retrying, like [`Retry::when`](https://docs.rs/backon/latest/backon/struct.Retry.html#method.when).
+/// - `notify`: the function to be notified before retrying, like [`Retry::notify`](https://docs.rs/backon/latest/backon/struct.Retry.html#method.notify).
+///
+/// Since the body could be executed many times, it can't move out of the arguments.
+///
+/// Receivers are supported, `&mut self` is passed through every attempt of an async function with
+/// `RetryableWithContext`, so the body can use it as usual.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use backon::retry;
+/// use backon::ConstantBuilder;
+///
+/// fn is_transient(err: &anyhow::Error) -> bool {
+///     err.to_string() == "retryable"
+/// }
+///
+/// struct Client {
+///     calls: usize,
+/// }
+///
+/// impl Client {
+///     #[retry(backoff = ConstantBuilder::default().with_max_times(5), when = is_transient)]
+///     async fn fetch(&mut self, path: &str) -> Result<String> {
+///         self.calls += 1;
+///         if self.calls < 3 {
+///             return Err(anyhow::anyhow!("retryable"));
+///         }
+///         Ok(format!("{path} fetched after {} calls", self.calls))
+///     }
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let mut client = Client { calls: 0 };
+///     let content = client.fetch("/index.html").await?;
+///     assert_eq!(content, "/index.html fetched after 3 calls");
+///
+///     Ok(())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn retry(args: TokenStream, input: TokenStream) -> TokenStream {
+    match expand(args.into(), input.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// The arguments of the `retry` attribute.
+struct Args {
+    backoff: Expr,
+    when: Option<Expr>,
+    notify: Option<Expr>,
+}
+
+impl Args {
+    fn parse(args: TokenStream2) -> syn::Result<Self> {
+        let metas =
+            Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(args.clone())?;
+
+        let (mut backoff, mut when, mut notify) = (None, None, None);
+        for meta in metas {
+            let slot = if meta.path.is_ident("backoff") {
+                &mut backoff
+            } else if meta.path.is_ident("when") {
+                &mut when
+            } else if meta.path.is_ident("notify") {
+                &mut notify
+            } else {
+                return Err(syn::Error::new_spanned(
+                    meta.path,
+                    "unknown argument, expected `backoff`, `when` or `notify`",
+                ));
+            };
+            if slot.replace(meta.value).is_some() {
+                return Err(syn::Error::new_spanned(meta.path, "duplicated argument"));
+            }
+        }
+
+        let backoff = backoff.ok_or_else(|| {
+            syn::Error::new_spanned(
+                &args,
+                "missing argument `backoff`, like `backoff = ExponentialBuilder::default()`",
+            )
+        })?;
+        Ok(Args {
+            backoff,
+            when,
+            notify,
+        })
+    }
+}
+
+fn expand(args: TokenStream2, input: TokenStream2) -> syn::Result<TokenStream2> {
+    let args = Args::parse(args)?;
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = syn::parse2::<ItemFn>(input)?;
+
+    let ret = match &sig.output {
+        ReturnType::Type(_, ty) => ty,
+        ReturnType::Default => {
+            return Err(syn::Error::new_spanned(
+                &sig,
+                "retried functions must return a `Result`",
+            ))
+        }
+    };
+    let mut_self = sig.inputs.iter().any(|arg| match arg {
+        FnArg::Receiver(receiver) => receiver.reference.is_some() && receiver.mutability.is_some(),
+        FnArg::Typed(_) => false,
+    });
+
+    let backoff = &args.backoff;
+    let when = args.when.iter();
+    let notify = args.notify.iter();
+
+    let body = match (sig.asyncness.is_some(), mut_self) {
+        (false, _) => quote! {
+            ::backon::BlockingRetryable::retry(|| -> #ret #block, #backoff)
+                #(.when(#when))*
+                #(.notify(#notify))*
+                .call()
+        },
+        (true, false) => quote! {
+            ::backon::Retryable::retry(
+                || async {
+                    let res: #ret = async #block.await;
+                    res
+                },
+                #backoff,
+            )
+            #(.when(#when))*
+            #(.notify(#notify))*
+            .await
+        },
+        // The future can't borrow `&mut self` from the closure, so it's passed through every attempt as context.
+        (true, true) => {
+            let block = replace_self(*block);
+            quote! {
+                // Make sure the closure takes `&mut self` with its lifetime, instead of any lifetime.
+                fn __backon_context<'a, S: ?Sized + 'a, F, Fut>(_: &&'a mut S, f: F) -> F
+                where
+                    F: FnMut(&'a mut S) -> Fut,
+                {
+                    f
+                }
+
+                let (_, res) = ::backon::RetryableWithContext::retry(
+                    __backon_context(&self, |__self| async {
+                        let __self = __self;
+                        let res: #ret = async #block.await;
+                        (__self, res)
+                    }),
+                    #backoff,
+                )
+                .context(self)
+                #(.when(#when))*
+                #(.notify(#notify))*
+                .await;
+                res
+            }
+        }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #body
+        }
+    })
+}
+
+/// Replace the receiver `self` in the block with `__self`.
+///
+/// Paths like `self::helper` and nested items, which have their own `self`, are left as is.
+fn replace_self(mut block: Block) -> Block {
+    ReplaceSelf.visit_block_mut(&mut block);
+    block
+}
+
+struct ReplaceSelf;
+
+impl VisitMut for ReplaceSelf {
+    fn visit_expr_path_mut(&mut self, expr: &mut ExprPath) {
+        if expr.qself.is_none() && expr.path.is_ident("self") {
+            let span = expr.path.segments[0].ident.span();
+            expr.path = Ident::new("__self", span).into();
+        }
+    }
+
+    fn visit_item_mut(&mut self, _: &mut Item) {}
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        mac.tokens = replace_self_tokens(mac.tokens.clone());
+    }
+}
+
+/// Replace `self` in the input of a macro with `__self`, unless it starts a path like `self::helper`.
+fn replace_self_tokens(tokens: TokenStream2) -> TokenStream2 {
+    let mut tokens = tokens.into_iter().peekable();
+    let mut replaced = Vec::new();
+    while let Some(tt) = tokens.next() {
+        replaced.push(match tt {
+            TokenTree::Ident(ident)
+                if ident == "self"
+                    && !matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == ':') =>
+            {
+                TokenTree::Ident(Ident::new("__self", ident.span()))
+            }
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    proc_macro2::Group::new(group.delimiter(), replace_self_tokens(group.stream()));
+                replaced.set_span(group.span());
+                TokenTree::Group(replaced)
+            }
+            tt => tt,
+        });
+    }
+    replaced.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_backoff() {
+        let err = expand(
+            quote!(when = is_transient),
+            quote!(
+                fn f() -> Result<(), ()> {
+                    Ok(())
+                }
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("missing argument `backoff`"));
+    }
+
+    #[test]
+    fn test_unknown_argument() {
+        let err = expand(
+            quote!(backoff = ExponentialBuilder::default(), sleep = sleep),
+            quote!(
+                fn f() -> Result<(), ()> {
+                    Ok(())
+                }
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown argument"));
+    }
+
+    #[test]
+    fn test_replace_self() {
+        let block = replace_self(syn::parse_quote!({
+            self.calls += 1;
+            self::helper(&mut *self);
+            fn nested(v: &Self) -> bool {
+                self::helper(v)
+            }
+            format!("{} {:?}", self.name, self::NAME)
+        }));
+        assert_eq!(
+            quote!(#block).to_string(),
+            quote!({
+                __self.calls += 1;
+                self::helper(&mut *__self);
+                fn nested(v: &Self) -> bool {
+                    self::helper(v)
+                }
+                format!("{} {:?}", __self.name, self::NAME)
+            })
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use anyhow::Result;
+use backon::retry;
+use backon::ConstantBuilder;
+
+fn is_transient(err: &anyhow::Error) -> bool {
+    err.to_string() == "retryable"
+}
+
+fn suffix() -> &'static str {
+    "!"
+}
+
+struct Client {
+    calls: usize,
+    succeed_at: usize,
+}
+
+impl Client {
+    fn attempt(&self) -> Result<usize> {
+        if self.calls < self.succeed_at {
+            return Err(anyhow::anyhow!("retryable"));
+        }
+        Ok(self.calls)
+    }
+
+    #[retry(backoff = ConstantBuilder::default().with_delay(Duration::from_millis(1)))]
+    async fn fetch(&mut self, name: String) -> Result<String> {
+        self.calls += 1;
+        let calls = self.attempt()?;
+        Ok(format!("{name} after {calls}"))
+    }
+
+    #[retry(backoff = ConstantBuilder::default().with_delay(Duration::from_millis(1)))]
+    async fn greet(&mut self) -> Result<String> {
+        self.calls += 1;
+        let calls = self.attempt()?;
+        Ok(format!("hello {calls}{}", self::suffix()))
+    }
+
+    #[retry(backoff = ConstantBuilder::default().with_delay(Duration::from_millis(1)), when = is_transient)]
+    async fn check(&self) -> Result<usize> {
+        Err(anyhow::anyhow!("fatal"))
+    }
+
+    #[retry(backoff = ConstantBuilder::default().with_delay(Duration::from_millis(1)))]
+    fn fetch_blocking(&mut self) -> Result<usize> {
+        self.calls += 1;
+        self.attempt()
+    }
+}
+
+#[retry(
+    backoff = ConstantBuilder::default().with_delay(Duration::from_millis(1)).with_max_times(2),
+    when = is_transient,
+    notify = |err: &anyhow::Error, dur: Duration| println!("retrying {err} after {dur:?}"),
+)]
+async fn always_fail(calls: &AtomicUsize) -> Result<()> {
+    calls.fetch_add(1, Ordering::Relaxed);
+    Err(anyhow::anyhow!("retryable"))
+}
+
+#[tokio::test]
+async fn test_retry_mut_self() {
+    let mut client = Client {
+        calls: 0,
+        succeed_at: 3,
+    };
+
+    let result = client.fetch("fetch".to_string()).await;
+    assert_eq!(result.unwrap(), "fetch after 3");
+    assert_eq!(client.calls, 3);
+}
+
+#[tokio::test]
+async fn test_retry_mut_self_with_self_path() {
+    let mut client = Client {
+        calls: 0,
+        succeed_at: 2,
+    };
+
+    let result = client.greet().await;
+    assert_eq!(result.unwrap(), "hello 2!");
+}
+
+#[tokio::test]
+async fn test_retry_self_not_retryable() {
+    let client = Client {
+        calls: 0,
+        succeed_at: 0,
+    };
+
+    let result = client.check().await;
+    assert_eq!(result.unwrap_err().to_string(), "fatal");
+}
+
+#[tokio::test]
+async fn test_retry_fn() {
+    let calls = AtomicUsize::new(0);
+
+    let result = always_fail(&calls).await;
+    assert_eq!(result.unwrap_err().to_string(), "retryable");
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn test_retry_blocking() {
+    let mut client = Client {
+        calls: 0,
+        succeed_at: 2,
+    };
+
+    assert_eq!(client.fetch_blocking().unwrap(), 2);
+    assert_eq!(client.calls, 2);
+}
@@ -0,0 +1,246 @@
+use core::time::Duration;
+
+use crate::backoff::BackoffBuilder;
+use crate::ConstantBackoff;
+use crate::ConstantBuilder;
+use crate::DecorrelatedJitterBackoff;
+use crate::DecorrelatedJitterBuilder;
+use crate::ExponentialBackoff;
+use crate::ExponentialBuilder;
+use crate::FibonacciBackoff;
+use crate::FibonacciBuilder;
+use crate::LinearBackoff;
+use crate::LinearBuilder;
+use crate::PolynomialBackoff;
+use crate::PolynomialBuilder;
+use crate::ScheduleBackoff;
+use crate::ScheduleBuilder;
+
+/// AnyBackoffBuilder is used to build an [`AnyBackoff`] with one of the built-in strategies picked at runtime.
+///
+/// Every builder converts into it with [`From`], so a strategy can be selected without boxing the backoff.
+///
+/// With the `serde` feature enabled, the strategy is tagged by the `strategy` field in `snake_case`,
+/// and the other fields are the ones of the chosen builder:
+///
+/// ```yaml
+/// strategy: exponential
+/// min_delay: 100ms
+/// max_times: 5
+/// ```
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::AnyBackoffBuilder;
+/// use backon::ConstantBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let backoff: AnyBackoffBuilder = if std::env::var("FAST_RETRY").is_ok() {
+///         ConstantBuilder::default()
+///             .with_delay(Duration::from_millis(10))
+///             .into()
+///     } else {
+///         ExponentialBuilder::default().into()
+///     };
+///
+///     let content = fetch.retry(&backoff).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "strategy", rename_all = "snake_case")
+)]
+pub enum AnyBackoffBuilder {
+    /// See [`ConstantBuilder`].
+    Constant(ConstantBuilder),
+    /// See [`ExponentialBuilder`].
+    Exponential(ExponentialBuilder),
+    /// See [`FibonacciBuilder`].
+    Fibonacci(FibonacciBuilder),
+    /// See [`LinearBuilder`].
+    Linear(LinearBuilder),
+    /// See [`PolynomialBuilder`].
+    Polynomial(PolynomialBuilder),
+    /// See [`ScheduleBuilder`].
+    Schedule(ScheduleBuilder),
+    /// See [`DecorrelatedJitterBuilder`].
+    DecorrelatedJitter(DecorrelatedJitterBuilder),
+}
+
+macro_rules! impl_from_builder {
+    ($($variant:ident($builder:ty)),* $(,)?) => {
+        $(
+            impl From<$builder> for AnyBackoffBuilder {
+                fn from(builder: $builder) -> Self {
+                    AnyBackoffBuilder::$variant(builder)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_builder!(
+    Constant(ConstantBuilder),
+    Exponential(ExponentialBuilder),
+    Fibonacci(FibonacciBuilder),
+    Linear(LinearBuilder),
+    Polynomial(PolynomialBuilder),
+    Schedule(ScheduleBuilder),
+    DecorrelatedJitter(DecorrelatedJitterBuilder),
+);
+
+impl BackoffBuilder for AnyBackoffBuilder {
+    type Backoff = AnyBackoff;
+
+    fn build(self) -> Self::Backoff {
+        match self {
+            AnyBackoffBuilder::Constant(b) => AnyBackoff::Constant(b.build()),
+            AnyBackoffBuilder::Exponential(b) => AnyBackoff::Exponential(b.build()),
+            AnyBackoffBuilder::Fibonacci(b) => AnyBackoff::Fibonacci(b.build()),
+            AnyBackoffBuilder::Linear(b) => AnyBackoff::Linear(b.build()),
+            AnyBackoffBuilder::Polynomial(b) => AnyBackoff::Polynomial(b.build()),
+            AnyBackoffBuilder::Schedule(b) => AnyBackoff::Schedule(b.build()),
+            AnyBackoffBuilder::DecorrelatedJitter(b) => AnyBackoff::DecorrelatedJitter(b.build()),
+        }
+    }
+}
+
+impl BackoffBuilder for &AnyBackoffBuilder {
+    type Backoff = AnyBackoff;
+
+    fn build(self) -> Self::Backoff {
+        self.clone().build()
+    }
+}
+
+/// AnyBackoff provides the delays of the strategy picked by [`AnyBackoffBuilder`].
+///
+/// This backoff strategy is constructed by [`AnyBackoffBuilder`].
+#[doc(hidden)]
+#[derive(Debug)]
+pub enum AnyBackoff {
+    /// See [`ConstantBackoff`].
+    Constant(ConstantBackoff),
+    /// See [`ExponentialBackoff`].
+    Exponential(ExponentialBackoff),
+    /// See [`FibonacciBackoff`].
+    Fibonacci(FibonacciBackoff),
+    /// See [`LinearBackoff`].
+    Linear(LinearBackoff),
+    /// See [`PolynomialBackoff`].
+    Polynomial(PolynomialBackoff),
+    /// See [`ScheduleBackoff`].
+    Schedule(ScheduleBackoff),
+    /// See [`DecorrelatedJitterBackoff`].
+    DecorrelatedJitter(DecorrelatedJitterBackoff),
+}
+
+impl Iterator for AnyBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            AnyBackoff::Constant(b) => b.next(),
+            AnyBackoff::Exponential(b) => b.next(),
+            AnyBackoff::Fibonacci(b) => b.next(),
+            AnyBackoff::Linear(b) => b.next(),
+            AnyBackoff::Polynomial(b) => b.next(),
+            AnyBackoff::Schedule(b) => b.next(),
+            AnyBackoff::DecorrelatedJitter(b) => b.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn test_any_backoff() {
+        let builder = AnyBackoffBuilder::from(
+            ConstantBuilder::default()
+                .with_delay(Duration::from_millis(10))
+                .with_max_times(2),
+        );
+        assert_eq!(
+            (&builder).build().collect::<Vec<_>>(),
+            [Duration::from_millis(10); 2]
+        );
+
+        let builder = AnyBackoffBuilder::from(ExponentialBuilder::default());
+        assert_eq!(
+            builder.build().collect::<Vec<_>>(),
+            ExponentialBuilder::default().build().collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_any_backoff_deserialize() {
+        use alloc::string::ToString;
+
+        use serde_json::json;
+
+        let builder: AnyBackoffBuilder = serde_json::from_value(json!({
+            "strategy": "exponential",
+            "min_delay": "1s",
+            "max_times": 2,
+        }))
+        .unwrap();
+        assert_eq!(
+            builder.build().collect::<Vec<_>>(),
+            [Duration::from_secs(1), Duration::from_secs(2)]
+        );
+
+        let builder: AnyBackoffBuilder = serde_json::from_value(json!({
+            "strategy": "schedule",
+            "delays": ["1s", "2s"],
+        }))
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&builder).unwrap(),
+            json!({
+                "strategy": "schedule",
+                "delays": ["1s", "2s"],
+                "jitter": "none",
+                "jitter_seed": null,
+            })
+        );
+
+        let err = serde_json::from_value::<AnyBackoffBuilder>(json!({ "strategy": "random" }))
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown variant"), "{err}");
+
+        let err = serde_json::from_value::<AnyBackoffBuilder>(json!({
+            "strategy": "constant",
+            "min_delay": "1s",
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown field"), "{err}");
+    }
+}
@@ -27,7 +27,10 @@ impl<B: Backoff> BackoffBuilder for B {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{ConstantBuilder, ExponentialBuilder, FibonacciBuilder};
+    use crate::{
+        AnyBackoffBuilder, ConstantBuilder, DecorrelatedJitterBuilder, ExponentialBuilder,
+        FibonacciBuilder, LinearBuilder, PolynomialBuilder, ScheduleBuilder,
+    };
 
     fn test_fn_builder(b: impl BackoffBuilder) {
         let _ = b.build();
@@ -42,7 +45,12 @@ mod tests {
         {
             test_fn_builder(&ConstantBuilder::default());
             test_fn_builder(&FibonacciBuilder::default());
+            test_fn_builder(&LinearBuilder::default());
+            test_fn_builder(&PolynomialBuilder::default());
+            test_fn_builder(&ScheduleBuilder::default());
             test_fn_builder(&ExponentialBuilder::default());
+            test_fn_builder(&DecorrelatedJitterBuilder::default());
+            test_fn_builder(&AnyBackoffBuilder::from(ExponentialBuilder::default()));
         }
     }
 }
@@ -1,6 +1,9 @@
 use core::time::Duration;
 
+use crate::backoff::builder_options;
 use crate::backoff::BackoffBuilder;
+use crate::backoff::DelayLimits;
+use crate::backoff::JitterMode;
 
 /// ConstantBuilder is used to create a [`ConstantBackoff`], providing a steady delay with a fixed number of retries.
 ///
@@ -8,6 +11,9 @@ use crate::backoff::BackoffBuilder;
 ///
 /// - delay: 1s
 /// - max_times: 3
+/// - total_delay: None
+/// - jitter: [`JitterMode::None`]
+/// - jitter_seed: random
 ///
 /// # Examples
 ///
@@ -32,10 +38,23 @@ use crate::backoff::BackoffBuilder;
 /// }
 /// ```
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct ConstantBuilder {
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration"))]
     delay: Duration,
     max_times: Option<usize>,
-    jitter: bool,
+    jitter: JitterMode,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    total_delay: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(rename = "jitter_seed"))]
+    seed: Option<u64>,
 }
 
 impl Default for ConstantBuilder {
@@ -43,7 +62,9 @@ impl Default for ConstantBuilder {
         Self {
             delay: Duration::from_secs(1),
             max_times: Some(3),
-            jitter: false,
+            jitter: JitterMode::None,
+            total_delay: None,
+            seed: None,
         }
     }
 }
@@ -54,42 +75,24 @@ impl ConstantBuilder {
         self.delay = delay;
         self
     }
-
-    /// Set the maximum number of attempts to be made.
-    pub fn with_max_times(mut self, max_times: usize) -> Self {
-        self.max_times = Some(max_times);
-        self
-    }
-
-    /// Set jitter for the backoff.
-    ///
-    /// Jitter is a random value added to the delay to prevent a thundering herd problem.
-    pub fn with_jitter(mut self) -> Self {
-        self.jitter = true;
-        self
-    }
-
-    /// Set no max times for the backoff.
-    ///
-    /// The backoff will not stop by itself.
-    ///
-    /// _The backoff could stop reaching `usize::MAX` attempts but this is **unrealistic**._
-    pub fn without_max_times(mut self) -> Self {
-        self.max_times = None;
-        self
-    }
 }
 
+builder_options!(ConstantBuilder: jitter, limits);
+
 impl BackoffBuilder for ConstantBuilder {
     type Backoff = ConstantBackoff;
 
     fn build(self) -> Self::Backoff {
         ConstantBackoff {
             delay: self.delay,
-            max_times: self.max_times,
-
-            attempts: 0,
-            jitter: self.jitter,
+            limits: DelayLimits::new(
+                self.jitter,
+                self.seed,
+                self.delay,
+                None,
+                self.max_times,
+                self.total_delay,
+            ),
         }
     }
 }
@@ -109,36 +112,21 @@ impl BackoffBuilder for &ConstantBuilder {
 #[derive(Debug)]
 pub struct ConstantBackoff {
     delay: Duration,
-    max_times: Option<usize>,
-
-    attempts: usize,
-    jitter: bool,
+    limits: DelayLimits,
 }
 
 impl Iterator for ConstantBackoff {
     type Item = Duration;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let delay = || match self.jitter {
-            true => self.delay + self.delay.mul_f32(fastrand::f32()),
-            false => self.delay,
-        };
-        match self.max_times {
-            None => Some(delay()),
-            Some(max_times) => {
-                if self.attempts >= max_times {
-                    None
-                } else {
-                    self.attempts += 1;
-                    Some(delay())
-                }
-            }
-        }
+        self.limits.next_attempt()?;
+        self.limits.delay(self.delay)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
     use core::time::Duration;
 
     #[cfg(target_arch = "wasm32")]
@@ -146,6 +134,7 @@ mod tests {
 
     use crate::BackoffBuilder;
     use crate::ConstantBuilder;
+    use crate::JitterMode;
 
     #[test]
     fn test_constant_default() {
@@ -177,15 +166,94 @@ mod tests {
         assert_eq!(None, it.next());
     }
 
+    #[test]
+    fn test_constant_with_total_delay() {
+        let mut it = ConstantBuilder::default()
+            .without_max_times()
+            .with_total_delay(Duration::from_millis(2500))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), it.next());
+        assert_eq!(Some(Duration::from_secs(1)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_constant_total_delay_fused() {
+        let mut it = ConstantBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(5))
+            .build();
+
+        while it.next().is_some() {}
+        // A later delay could fit into the total delay again, but the backoff must stay exhausted.
+        for _ in 0..100 {
+            assert_eq!(None, it.next());
+        }
+    }
+
     #[test]
     fn test_constant_with_jitter() {
         let mut it = ConstantBuilder::default().with_jitter().build();
 
         let dur = it.next().unwrap();
-        fastrand::seed(7);
         assert!(dur > Duration::from_secs(1));
     }
 
+    #[test]
+    fn test_constant_with_jitter_seed() {
+        let builder = ConstantBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(7)
+            .without_max_times();
+
+        let it1: Vec<_> = builder.build().take(100).collect();
+        let it2: Vec<_> = builder.build().take(100).collect();
+        assert_eq!(it1, it2);
+    }
+
+    #[test]
+    fn test_constant_with_rng() {
+        let builder = |rng| {
+            ConstantBuilder::default()
+                .with_jitter()
+                .with_rng(rng)
+                .without_max_times()
+        };
+
+        // A generator seeded the same way always yields the same delays.
+        let it1: Vec<_> = builder(fastrand::Rng::with_seed(7))
+            .build()
+            .take(100)
+            .collect();
+        let it2: Vec<_> = builder(fastrand::Rng::with_seed(7))
+            .build()
+            .take(100)
+            .collect();
+        assert_eq!(it1, it2);
+
+        let it3: Vec<_> = builder(fastrand::Rng::with_seed(8))
+            .build()
+            .take(100)
+            .collect();
+        assert_ne!(it1, it3);
+    }
+
+    #[test]
+    fn test_constant_with_jitter_mode() {
+        let mut it = ConstantBuilder::default()
+            .with_jitter_mode(JitterMode::Equal)
+            .build();
+
+        for _ in 0..3 {
+            let v = it.next().expect("value must valid");
+            assert!(v >= Duration::from_millis(500), "current: {v:?}");
+            assert!(v < Duration::from_secs(1), "current: {v:?}");
+        }
+        assert_eq!(None, it.next());
+    }
+
     #[test]
     fn test_constant_without_max_times() {
         let mut it = ConstantBuilder::default().without_max_times().build();
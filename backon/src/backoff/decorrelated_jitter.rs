@@ -0,0 +1,267 @@
+use core::time::Duration;
+
+use crate::backoff::builder_options;
+use crate::backoff::exponential::saturating_mul;
+use crate::backoff::BackoffBuilder;
+use crate::backoff::DelayLimits;
+use crate::backoff::JitterMode;
+
+/// DecorrelatedJitterBuilder is used to construct a [`DecorrelatedJitterBackoff`] that offers
+/// delays with decorrelated jitter.
+///
+/// Every delay is drawn from `random(min_delay, prev_delay * 3)` and capped at `max_delay`,
+/// as described in AWS's [Exponential Backoff And Jitter](https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/).
+/// Clients that share the same settings won't keep retrying at the same moments.
+///
+/// # Default
+///
+/// - min_delay: 1s
+/// - max_delay: 60s
+/// - max_times: 3
+/// - total_delay: None
+/// - jitter_seed: random
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::DecorrelatedJitterBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch.retry(DecorrelatedJitterBuilder::default()).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct DecorrelatedJitterBuilder {
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration"))]
+    min_delay: Duration,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    max_delay: Option<Duration>,
+    max_times: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    total_delay: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(rename = "jitter_seed"))]
+    seed: Option<u64>,
+}
+
+impl Default for DecorrelatedJitterBuilder {
+    fn default() -> Self {
+        Self {
+            min_delay: Duration::from_secs(1),
+            max_delay: Some(Duration::from_secs(60)),
+            max_times: Some(3),
+            total_delay: None,
+            seed: None,
+        }
+    }
+}
+
+builder_options!(DecorrelatedJitterBuilder: delay, limits);
+
+impl BackoffBuilder for DecorrelatedJitterBuilder {
+    type Backoff = DecorrelatedJitterBackoff;
+
+    fn build(self) -> Self::Backoff {
+        DecorrelatedJitterBackoff {
+            // The delays are random by themselves, so no jitter is applied on top of them.
+            limits: DelayLimits::new(
+                JitterMode::None,
+                self.seed,
+                self.min_delay,
+                self.max_delay,
+                self.max_times,
+                self.total_delay,
+            ),
+
+            previous_delay: None,
+        }
+    }
+}
+
+impl BackoffBuilder for &DecorrelatedJitterBuilder {
+    type Backoff = DecorrelatedJitterBackoff;
+
+    fn build(self) -> Self::Backoff {
+        (*self).build()
+    }
+}
+
+/// DecorrelatedJitterBackoff provides a delay with decorrelated jitter.
+///
+/// This backoff strategy is constructed by [`DecorrelatedJitterBuilder`].
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct DecorrelatedJitterBackoff {
+    limits: DelayLimits,
+
+    previous_delay: Option<Duration>,
+}
+
+impl Iterator for DecorrelatedJitterBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.limits.next_attempt()?;
+
+        // If previous_delay is None, it's must be the first time to retry.
+        let min_delay = self.limits.min_delay();
+        let prev = self.previous_delay.unwrap_or(min_delay);
+        let upper = saturating_mul(prev, 3.0).max(min_delay);
+
+        let delay = min_delay.saturating_add((upper - min_delay).mul_f32(self.limits.rng().f32()));
+        let delay = self.limits.delay(delay)?;
+        self.previous_delay = Some(delay);
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use crate::BackoffBuilder;
+    use crate::DecorrelatedJitterBuilder;
+
+    #[test]
+    fn test_decorrelated_jitter_default() {
+        let mut it = DecorrelatedJitterBuilder::default().build();
+
+        let mut prev = Duration::from_secs(1);
+        for _ in 0..3 {
+            let v = it.next().expect("value must valid");
+            assert!(v >= Duration::from_secs(1), "current: {v:?}");
+            assert!(v <= prev * 3, "current: {v:?}, previous: {prev:?}");
+            prev = v;
+        }
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_max_delay() {
+        let mut it = DecorrelatedJitterBuilder::default()
+            .with_max_delay(Duration::from_secs(2))
+            .without_max_times()
+            .build();
+
+        for _ in 0..1_000 {
+            let v = it.next().expect("value must valid");
+            assert!(v >= Duration::from_secs(1), "current: {v:?}");
+            assert!(v <= Duration::from_secs(2), "current: {v:?}");
+        }
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_min_delay_equals_max_delay() {
+        let mut it = DecorrelatedJitterBuilder::default()
+            .with_min_delay(Duration::from_millis(500))
+            .with_max_delay(Duration::from_millis(500))
+            .build();
+
+        assert_eq!(Some(Duration::from_millis(500)), it.next());
+        assert_eq!(Some(Duration::from_millis(500)), it.next());
+        assert_eq!(Some(Duration::from_millis(500)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_no_max_delay() {
+        let mut it = DecorrelatedJitterBuilder::default()
+            .with_min_delay(Duration::MAX)
+            .without_max_delay()
+            .build();
+
+        assert_eq!(Some(Duration::MAX), it.next());
+        assert_eq!(Some(Duration::MAX), it.next());
+        assert_eq!(Some(Duration::MAX), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_seed() {
+        let builder = DecorrelatedJitterBuilder::default()
+            .with_jitter_seed(42)
+            .without_max_times();
+
+        let it1: Vec<_> = builder.build().take(100).collect();
+        let it2: Vec<_> = builder.build().take(100).collect();
+        assert_eq!(it1, it2);
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_total_delay() {
+        let it = DecorrelatedJitterBuilder::default()
+            .with_max_delay(Duration::from_secs(2))
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(10))
+            .build();
+
+        let total: Duration = it.sum();
+        assert!(total <= Duration::from_secs(10), "total: {total:?}");
+        assert!(total > Duration::from_secs(8), "total: {total:?}");
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_total_delay_fused() {
+        let mut it = DecorrelatedJitterBuilder::default()
+            .with_min_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_secs(2))
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(5))
+            .build();
+
+        while it.next().is_some() {}
+        // A later delay could fit into the total delay again, but the backoff must stay exhausted.
+        for _ in 0..100 {
+            assert_eq!(None, it.next());
+        }
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_max_times() {
+        let mut it = DecorrelatedJitterBuilder::default()
+            .with_max_times(1)
+            .build();
+
+        assert!(it.next().is_some());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_no_max_times() {
+        let mut it = DecorrelatedJitterBuilder::default()
+            .with_min_delay(Duration::ZERO)
+            .without_max_times()
+            .build();
+
+        for _ in 0..10_000 {
+            assert_eq!(Some(Duration::ZERO), it.next());
+        }
+    }
+}
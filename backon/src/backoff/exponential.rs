@@ -1,16 +1,21 @@
 use core::time::Duration;
 
+use crate::backoff::builder_options;
 use crate::backoff::BackoffBuilder;
+use crate::backoff::DelayLimits;
+use crate::backoff::JitterMode;
 
 /// ExponentialBuilder is used to construct an [`ExponentialBackoff`] that offers delays with exponential retries.
 ///
 /// # Default
 ///
-/// - jitter: false
+/// - jitter: [`JitterMode::None`]
 /// - factor: 2
 /// - min_delay: 1s
 /// - max_delay: 60s
 /// - max_times: 3
+/// - total_delay: None
+/// - jitter_seed: random
 ///
 /// # Examples
 ///
@@ -35,36 +40,47 @@ use crate::backoff::BackoffBuilder;
 /// }
 /// ```
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct ExponentialBuilder {
-    jitter: bool,
+    jitter: JitterMode,
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_factor"))]
     factor: f32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration"))]
     min_delay: Duration,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
     max_delay: Option<Duration>,
     max_times: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    total_delay: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(rename = "jitter_seed"))]
+    seed: Option<u64>,
 }
 
 impl Default for ExponentialBuilder {
     fn default() -> Self {
         Self {
-            jitter: false,
+            jitter: JitterMode::None,
             factor: 2.0,
             min_delay: Duration::from_secs(1),
             max_delay: Some(Duration::from_secs(60)),
             max_times: Some(3),
+            total_delay: None,
+            seed: None,
         }
     }
 }
 
 impl ExponentialBuilder {
-    /// Set the jitter for the backoff.
-    ///
-    /// When jitter is enabled, [`ExponentialBackoff`] will add a random jitter within `(0, min_delay)`
-    /// to the current delay.
-    pub fn with_jitter(mut self) -> Self {
-        self.jitter = true;
-        self
-    }
-
     /// Set the factor for the backoff.
     ///
     /// # Panics
@@ -76,63 +92,26 @@ impl ExponentialBuilder {
         self.factor = factor;
         self
     }
-
-    /// Set the minimum delay for the backoff.
-    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
-        self.min_delay = min_delay;
-        self
-    }
-
-    /// Set the maximum delay for the backoff.
-    ///
-    /// The delay will not increase if the current delay exceeds the maximum delay.
-    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
-        self.max_delay = Some(max_delay);
-        self
-    }
-
-    /// Set no maximum delay for the backoff.
-    ///
-    /// The delay will keep increasing.
-    ///
-    /// _The delay will saturate at `Duration::MAX` which is an **unrealistic** delay._
-    pub fn without_max_delay(mut self) -> Self {
-        self.max_delay = None;
-        self
-    }
-
-    /// Set the maximum number of attempts for the current backoff.
-    ///
-    /// The backoff will stop if the maximum number of attempts is reached.
-    pub fn with_max_times(mut self, max_times: usize) -> Self {
-        self.max_times = Some(max_times);
-        self
-    }
-
-    /// Set no maximum number of attempts for the current backoff.
-    ///
-    /// The backoff will not stop by itself.
-    ///
-    /// _The backoff could stop reaching `usize::MAX` attempts but this is **unrealistic**._
-    pub fn without_max_times(mut self) -> Self {
-        self.max_times = None;
-        self
-    }
 }
 
+builder_options!(ExponentialBuilder: jitter, delay, limits);
+
 impl BackoffBuilder for ExponentialBuilder {
     type Backoff = ExponentialBackoff;
 
     fn build(self) -> Self::Backoff {
         ExponentialBackoff {
-            jitter: self.jitter,
             factor: self.factor,
-            min_delay: self.min_delay,
-            max_delay: self.max_delay,
-            max_times: self.max_times,
+            limits: DelayLimits::new(
+                self.jitter,
+                self.seed,
+                self.min_delay,
+                self.max_delay,
+                self.max_times,
+                self.total_delay,
+            ),
 
             current_delay: None,
-            attempts: 0,
         }
     }
 }
@@ -151,53 +130,45 @@ impl BackoffBuilder for &ExponentialBuilder {
 #[doc(hidden)]
 #[derive(Debug)]
 pub struct ExponentialBackoff {
-    jitter: bool,
     factor: f32,
-    min_delay: Duration,
-    max_delay: Option<Duration>,
-    max_times: Option<usize>,
+    limits: DelayLimits,
 
     current_delay: Option<Duration>,
-    attempts: usize,
 }
 
 impl Iterator for ExponentialBackoff {
     type Item = Duration;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.attempts >= self.max_times.unwrap_or(usize::MAX) {
-            return None;
-        }
-        self.attempts += 1;
+        self.limits.next_attempt()?;
 
-        let mut tmp_cur = match self.current_delay {
-            None => {
-                // If current_delay is None, it's must be the first time to retry.
-                self.current_delay = Some(self.min_delay);
-                self.min_delay
-            }
-            Some(mut cur) => {
+        let cur = match self.current_delay {
+            // If current_delay is None, it's must be the first time to retry.
+            None => self.limits.min_delay(),
+            Some(cur) => {
                 // If current delay larger than max delay, we should stop increment anymore.
-                if let Some(max_delay) = self.max_delay {
-                    if cur < max_delay {
-                        cur = saturating_mul(cur, self.factor);
-                    }
-                    if cur > max_delay {
-                        cur = max_delay;
-                    }
+                if cur < self.limits.max_delay().unwrap_or(Duration::MAX) {
+                    saturating_mul(cur, self.factor)
                 } else {
-                    cur = saturating_mul(cur, self.factor);
+                    cur
                 }
-                self.current_delay = Some(cur);
-                cur
             }
         };
-        // If jitter is enabled, add random jitter based on min delay.
-        if self.jitter {
-            tmp_cur = tmp_cur.saturating_add(self.min_delay.mul_f32(fastrand::f32()));
-        }
-        Some(tmp_cur)
+        self.current_delay = Some(cur);
+        self.limits.delay(cur)
+    }
+}
+
+/// Reject factors that [`ExponentialBuilder::with_factor`] would not accept.
+#[cfg(feature = "serde")]
+fn deserialize_factor<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let factor = <f32 as serde::Deserialize>::deserialize(deserializer)?;
+    if !factor.is_finite() || factor < 1.0 {
+        return Err(serde::de::Error::custom(alloc::format!(
+            "invalid factor `{factor}`, expected a finite number no less than 1"
+        )));
     }
+    Ok(factor)
 }
 
 #[inline]
@@ -207,6 +178,7 @@ pub(crate) fn saturating_mul(d: Duration, rhs: f32) -> Duration {
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
     use core::time::Duration;
 
     #[cfg(target_arch = "wasm32")]
@@ -214,6 +186,7 @@ mod tests {
 
     use crate::BackoffBuilder;
     use crate::ExponentialBuilder;
+    use crate::JitterMode;
 
     #[test]
     fn test_exponential_default() {
@@ -254,6 +227,57 @@ mod tests {
         assert_eq!(None, exp.next());
     }
 
+    #[test]
+    fn test_exponential_full_jitter() {
+        let mut exp = ExponentialBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .build();
+
+        let v = exp.next().expect("value must valid");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = exp.next().expect("value must valid");
+        assert!(v < Duration::from_secs(2), "current: {v:?}");
+
+        let v = exp.next().expect("value must valid");
+        assert!(v < Duration::from_secs(4), "current: {v:?}");
+
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_equal_jitter() {
+        let mut exp = ExponentialBuilder::default()
+            .with_jitter_mode(JitterMode::Equal)
+            .build();
+
+        let v = exp.next().expect("value must valid");
+        assert!(v >= Duration::from_millis(500), "current: {v:?}");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = exp.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(1), "current: {v:?}");
+        assert!(v < Duration::from_secs(2), "current: {v:?}");
+
+        let v = exp.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(2), "current: {v:?}");
+        assert!(v < Duration::from_secs(4), "current: {v:?}");
+
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_jitter_seed() {
+        let builder = ExponentialBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(42)
+            .without_max_times();
+
+        let exp1: Vec<_> = builder.build().take(100).collect();
+        let exp2: Vec<_> = builder.build().take(100).collect();
+        assert_eq!(exp1, exp2);
+    }
+
     #[test]
     fn test_exponential_min_delay() {
         let mut exp = ExponentialBuilder::default()
@@ -312,11 +336,13 @@ mod tests {
     #[test]
     fn test_exponential_max_delay_without_default_1() {
         let mut exp = ExponentialBuilder {
-            jitter: false,
+            jitter: JitterMode::None,
             factor: 10_000_000_000_f32,
             min_delay: Duration::from_secs(1),
             max_delay: None,
             max_times: None,
+            total_delay: None,
+            seed: None,
         }
         .build();
 
@@ -329,11 +355,13 @@ mod tests {
     #[test]
     fn test_exponential_max_delay_without_default_2() {
         let mut exp = ExponentialBuilder {
-            jitter: true,
+            jitter: JitterMode::Additive,
             factor: 10_000_000_000_f32,
             min_delay: Duration::from_secs(10_000_000_000),
             max_delay: None,
             max_times: Some(2),
+            total_delay: None,
+            seed: None,
         }
         .build();
         let v = exp.next().expect("value must valid");
@@ -346,11 +374,13 @@ mod tests {
     #[test]
     fn test_exponential_max_delay_without_default_3() {
         let mut exp = ExponentialBuilder {
-            jitter: false,
+            jitter: JitterMode::None,
             factor: 10_000_000_000_f32,
             min_delay: Duration::from_secs(10_000_000_000),
             max_delay: Some(Duration::from_secs(60_000_000_000)),
             max_times: Some(3),
+            total_delay: None,
+            seed: None,
         }
         .build();
         assert_eq!(Some(Duration::from_secs(10_000_000_000)), exp.next());
@@ -359,6 +389,35 @@ mod tests {
         assert_eq!(None, exp.next());
     }
 
+    #[test]
+    fn test_exponential_total_delay() {
+        let mut exp = ExponentialBuilder::default()
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(10))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), exp.next());
+        assert_eq!(Some(Duration::from_secs(2)), exp.next());
+        assert_eq!(Some(Duration::from_secs(4)), exp.next());
+        // 1 + 2 + 4 + 8 = 15 > 10
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_total_delay_fused() {
+        let mut it = ExponentialBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(5))
+            .build();
+
+        while it.next().is_some() {}
+        // A later delay could fit into the total delay again, but the backoff must stay exhausted.
+        for _ in 0..100 {
+            assert_eq!(None, it.next());
+        }
+    }
+
     #[test]
     fn test_exponential_max_times() {
         let mut exp = ExponentialBuilder::default().with_max_times(1).build();
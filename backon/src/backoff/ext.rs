@@ -0,0 +1,294 @@
+use core::time::Duration;
+
+use crate::backoff::jitter_rng;
+use crate::backoff::Backoff;
+use crate::backoff::JitterMode;
+use crate::backoff::TotalDelay;
+
+/// BackoffExt provides combinators for every [`Backoff`].
+///
+/// The combinators return backoffs as well, so they can be passed to `retry` directly, or be combined further.
+/// Since a backoff is an [`Iterator`], the adapters of iterators can be used too, for example
+/// [`Iterator::chain`] to continue with another backoff once the first one is exhausted, or
+/// [`Iterator::skip`] to start from a later delay.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::BackoffBuilder;
+/// use backon::BackoffExt;
+/// use backon::ConstantBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::JitterMode;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // 3 fast retries, then exponential retries for at most 1 minute in total.
+///     let backoff = ConstantBuilder::default()
+///         .with_delay(Duration::from_millis(10))
+///         .build()
+///         .chain(ExponentialBuilder::default().without_max_times().build())
+///         .max_delay(Duration::from_secs(10))
+///         .with_added_jitter(JitterMode::Equal)
+///         .take_total(Duration::from_secs(60));
+///
+///     let content = fetch.retry(backoff).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+pub trait BackoffExt: Backoff + Sized {
+    /// Cap every delay at `max`.
+    fn max_delay(self, max: Duration) -> MaxDelay<Self> {
+        MaxDelay { inner: self, max }
+    }
+
+    /// Raise every delay to at least `min`.
+    fn min_delay(self, min: Duration) -> MinDelay<Self> {
+        MinDelay { inner: self, min }
+    }
+
+    /// Multiply every delay by `factor`.
+    ///
+    /// Negative factors are treated as `0`, and delays saturate at [`Duration::MAX`].
+    fn scale(self, factor: f32) -> Scale<Self> {
+        Scale {
+            inner: self,
+            factor: factor.max(0.0),
+        }
+    }
+
+    /// Stop once the sum of all returned delays would exceed `total`.
+    fn take_total(self, total: Duration) -> TakeTotal<Self> {
+        TakeTotal {
+            inner: self,
+            total: TotalDelay::new(Some(total)),
+        }
+    }
+
+    /// Apply a random jitter to every delay.
+    ///
+    /// For [`JitterMode::Additive`], the jitter is within `(0, delay)`.
+    fn with_added_jitter(self, mode: JitterMode) -> AddedJitter<Self> {
+        AddedJitter {
+            inner: self,
+            mode,
+            rng: jitter_rng(None),
+        }
+    }
+
+    /// Apply a random jitter to every delay, using the given seed for the random number generator.
+    ///
+    /// Backoffs with the same seed will always yield the same sequence of delays, which is useful
+    /// to make tests reproducible.
+    fn with_added_jitter_seeded(self, mode: JitterMode, seed: u64) -> AddedJitter<Self> {
+        AddedJitter {
+            inner: self,
+            mode,
+            rng: jitter_rng(Some(seed)),
+        }
+    }
+}
+
+impl<B: Backoff> BackoffExt for B {}
+
+/// Backoff returned by [`BackoffExt::max_delay`].
+#[derive(Debug, Clone)]
+pub struct MaxDelay<B> {
+    inner: B,
+    max: Duration,
+}
+
+impl<B: Backoff> Iterator for MaxDelay<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|delay| delay.min(self.max))
+    }
+}
+
+/// Backoff returned by [`BackoffExt::min_delay`].
+#[derive(Debug, Clone)]
+pub struct MinDelay<B> {
+    inner: B,
+    min: Duration,
+}
+
+impl<B: Backoff> Iterator for MinDelay<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|delay| delay.max(self.min))
+    }
+}
+
+/// Backoff returned by [`BackoffExt::scale`].
+#[derive(Debug, Clone)]
+pub struct Scale<B> {
+    inner: B,
+    factor: f32,
+}
+
+impl<B: Backoff> Iterator for Scale<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|delay| {
+            Duration::try_from_secs_f64(delay.as_secs_f64() * f64::from(self.factor))
+                .unwrap_or(Duration::MAX)
+        })
+    }
+}
+
+/// Backoff returned by [`BackoffExt::take_total`].
+#[derive(Debug, Clone)]
+pub struct TakeTotal<B> {
+    inner: B,
+    total: TotalDelay,
+}
+
+impl<B: Backoff> Iterator for TakeTotal<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.total.is_exhausted() {
+            return None;
+        }
+        let delay = self.inner.next()?;
+        self.total.take(delay)
+    }
+}
+
+/// Backoff returned by [`BackoffExt::with_added_jitter`].
+#[derive(Debug, Clone)]
+pub struct AddedJitter<B> {
+    inner: B,
+    mode: JitterMode,
+    rng: fastrand::Rng,
+}
+
+impl<B: Backoff> Iterator for AddedJitter<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|delay| self.mode.apply(delay, delay, &mut self.rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+    use crate::BackoffBuilder;
+    use crate::ConstantBuilder;
+    use crate::ExponentialBuilder;
+
+    fn secs(secs: &[u64]) -> Vec<Duration> {
+        secs.iter().copied().map(Duration::from_secs).collect()
+    }
+
+    #[test]
+    fn test_max_and_min_delay() {
+        let backoff = secs(&[1, 2, 4, 8])
+            .into_iter()
+            .max_delay(Duration::from_secs(5))
+            .min_delay(Duration::from_secs(2));
+
+        assert_eq!(backoff.collect::<Vec<_>>(), secs(&[2, 2, 4, 5]));
+    }
+
+    #[test]
+    fn test_scale() {
+        let backoff = secs(&[1, 2]).into_iter().scale(1.5);
+        assert_eq!(
+            backoff.collect::<Vec<_>>(),
+            vec![Duration::from_millis(1500), Duration::from_secs(3)]
+        );
+
+        let backoff = secs(&[1]).into_iter().scale(-1.0);
+        assert_eq!(backoff.collect::<Vec<_>>(), vec![Duration::ZERO]);
+
+        let backoff = vec![Duration::MAX].into_iter().scale(2.0);
+        assert_eq!(backoff.collect::<Vec<_>>(), vec![Duration::MAX]);
+    }
+
+    #[test]
+    fn test_take_total() {
+        let backoff = secs(&[1, 2, 3, 4])
+            .into_iter()
+            .take_total(Duration::from_secs(6));
+        assert_eq!(backoff.collect::<Vec<_>>(), secs(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_take_total_fused() {
+        let mut backoff = secs(&[1, 4, 1])
+            .into_iter()
+            .take_total(Duration::from_secs(3));
+        assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+        // The last delay would fit, but the backoff must stay exhausted.
+        assert_eq!(backoff.next(), None);
+        assert_eq!(backoff.next(), None);
+    }
+
+    #[test]
+    fn test_added_jitter() {
+        let backoff = vec![Duration::from_secs(4); 100]
+            .into_iter()
+            .with_added_jitter(JitterMode::Equal);
+        for delay in backoff {
+            assert!(delay >= Duration::from_secs(2), "current: {delay:?}");
+            assert!(delay < Duration::from_secs(4), "current: {delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_added_jitter_seeded() {
+        let backoff = || {
+            vec![Duration::from_secs(4); 10]
+                .into_iter()
+                .with_added_jitter_seeded(JitterMode::Full, 42)
+        };
+        assert_eq!(backoff().collect::<Vec<_>>(), backoff().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_chain() {
+        let backoff = ConstantBuilder::default()
+            .with_delay(Duration::from_millis(10))
+            .build()
+            .chain(ExponentialBuilder::default().with_max_times(2).build())
+            .max_delay(Duration::from_secs(1));
+
+        assert_eq!(
+            backoff.collect::<Vec<_>>(),
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(10),
+                Duration::from_millis(10),
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+            ]
+        );
+    }
+}
@@ -1,15 +1,20 @@
 use core::time::Duration;
 
+use crate::backoff::builder_options;
 use crate::backoff::BackoffBuilder;
+use crate::backoff::DelayLimits;
+use crate::backoff::JitterMode;
 
 /// FibonacciBuilder is used to build a [`FibonacciBackoff`] which offers a delay with Fibonacci-based retries.
 ///
 /// # Default
 ///
-/// - jitter: false
+/// - jitter: [`JitterMode::None`]
 /// - min_delay: 1s
 /// - max_delay: 60s
 /// - max_times: 3
+/// - total_delay: None
+/// - jitter_seed: random
 ///
 /// # Examples
 ///
@@ -34,89 +39,61 @@ use crate::backoff::BackoffBuilder;
 /// }
 /// ```
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct FibonacciBuilder {
-    jitter: bool,
+    jitter: JitterMode,
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration"))]
     min_delay: Duration,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
     max_delay: Option<Duration>,
     max_times: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    total_delay: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(rename = "jitter_seed"))]
+    seed: Option<u64>,
 }
 
 impl Default for FibonacciBuilder {
     fn default() -> Self {
         Self {
-            jitter: false,
+            jitter: JitterMode::None,
             min_delay: Duration::from_secs(1),
             max_delay: Some(Duration::from_secs(60)),
             max_times: Some(3),
+            total_delay: None,
+            seed: None,
         }
     }
 }
 
-impl FibonacciBuilder {
-    /// Set the jitter for the backoff.
-    ///
-    /// When jitter is enabled, FibonacciBackoff will add a random jitter between `(0, min_delay)` to the delay.
-    pub fn with_jitter(mut self) -> Self {
-        self.jitter = true;
-        self
-    }
-
-    /// Set the minimum delay for the backoff.
-    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
-        self.min_delay = min_delay;
-        self
-    }
-
-    /// Set the maximum delay for the current backoff.
-    ///
-    /// The delay will not increase if the current delay exceeds the maximum delay.
-    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
-        self.max_delay = Some(max_delay);
-        self
-    }
-
-    /// Set no maximum delay for the backoff.
-    ///
-    /// The delay will keep increasing.
-    ///
-    /// _The delay will saturate at `Duration::MAX` which is an **unrealistic** delay._
-    pub fn without_max_delay(mut self) -> Self {
-        self.max_delay = None;
-        self
-    }
-
-    /// Set the maximum number of attempts for the current backoff.
-    ///
-    /// The backoff will stop if the maximum number of attempts is reached.
-    pub fn with_max_times(mut self, max_times: usize) -> Self {
-        self.max_times = Some(max_times);
-        self
-    }
-
-    /// Set no maximum number of attempts for the current backoff.
-    ///
-    /// The backoff will not stop by itself.
-    ///
-    /// _The backoff could stop reaching `usize::MAX` attempts but this is **unrealistic**._
-    pub fn without_max_times(mut self) -> Self {
-        self.max_times = None;
-        self
-    }
-}
+builder_options!(FibonacciBuilder: jitter, delay, limits);
 
 impl BackoffBuilder for FibonacciBuilder {
     type Backoff = FibonacciBackoff;
 
     fn build(self) -> Self::Backoff {
         FibonacciBackoff {
-            jitter: self.jitter,
-            min_delay: self.min_delay,
-            max_delay: self.max_delay,
-            max_times: self.max_times,
+            limits: DelayLimits::new(
+                self.jitter,
+                self.seed,
+                self.min_delay,
+                self.max_delay,
+                self.max_times,
+                self.total_delay,
+            ),
 
             previous_delay: None,
             current_delay: None,
-            attempts: 0,
         }
     }
 }
@@ -135,63 +112,46 @@ impl BackoffBuilder for &FibonacciBuilder {
 #[doc(hidden)]
 #[derive(Debug)]
 pub struct FibonacciBackoff {
-    jitter: bool,
-    min_delay: Duration,
-    max_delay: Option<Duration>,
-    max_times: Option<usize>,
+    limits: DelayLimits,
 
     previous_delay: Option<Duration>,
     current_delay: Option<Duration>,
-    attempts: usize,
 }
 
 impl Iterator for FibonacciBackoff {
     type Item = Duration;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.attempts >= self.max_times.unwrap_or(usize::MAX) {
-            return None;
-        }
-        self.attempts += 1;
+        self.limits.next_attempt()?;
 
-        match self.current_delay {
+        let next = match self.current_delay {
             None => {
                 // If current_delay is None, it's must be the first time to retry.
-                let mut next = self.min_delay;
+                let next = self.limits.min_delay();
                 self.current_delay = Some(next);
-
-                // If jitter is enabled, add random jitter based on min delay.
-                if self.jitter {
-                    next += self.min_delay.mul_f32(fastrand::f32());
-                }
-
-                Some(next)
+                next
             }
             Some(cur) => {
                 let mut next = cur;
 
                 // If current delay larger than max delay, we should stop increment anymore.
-                if next < self.max_delay.unwrap_or(Duration::MAX) {
+                if next < self.limits.max_delay().unwrap_or(Duration::MAX) {
                     if let Some(prev) = self.previous_delay {
                         next = next.saturating_add(prev);
                         self.current_delay = Some(next);
                     }
                     self.previous_delay = Some(cur);
                 }
-
-                // If jitter is enabled, add random jitter based on min delay.
-                if self.jitter {
-                    next += self.min_delay.mul_f32(fastrand::f32());
-                }
-
-                Some(next)
+                next
             }
-        }
+        };
+        self.limits.delay(next)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
     use core::time::Duration;
 
     #[cfg(target_arch = "wasm32")]
@@ -199,6 +159,7 @@ mod tests {
 
     use crate::BackoffBuilder;
     use crate::FibonacciBuilder;
+    use crate::JitterMode;
 
     #[test]
     fn test_fibonacci_default() {
@@ -229,6 +190,57 @@ mod tests {
         assert_eq!(None, fib.next());
     }
 
+    #[test]
+    fn test_fibonacci_full_jitter() {
+        let mut fib = FibonacciBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .build();
+
+        let v = fib.next().expect("value must valid");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = fib.next().expect("value must valid");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = fib.next().expect("value must valid");
+        assert!(v < Duration::from_secs(2), "current: {v:?}");
+
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    fn test_fibonacci_equal_jitter() {
+        let mut fib = FibonacciBuilder::default()
+            .with_jitter_mode(JitterMode::Equal)
+            .build();
+
+        let v = fib.next().expect("value must valid");
+        assert!(v >= Duration::from_millis(500), "current: {v:?}");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = fib.next().expect("value must valid");
+        assert!(v >= Duration::from_millis(500), "current: {v:?}");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = fib.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(1), "current: {v:?}");
+        assert!(v < Duration::from_secs(2), "current: {v:?}");
+
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    fn test_fibonacci_jitter_seed() {
+        let builder = FibonacciBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(42)
+            .without_max_times();
+
+        let fib1: Vec<_> = builder.build().take(100).collect();
+        let fib2: Vec<_> = builder.build().take(100).collect();
+        assert_eq!(fib1, fib2);
+    }
+
     #[test]
     fn test_fibonacci_min_delay() {
         let mut fib = FibonacciBuilder::default()
@@ -255,6 +267,27 @@ mod tests {
         assert_eq!(None, fib.next());
     }
 
+    #[test]
+    fn test_fibonacci_max_delay_clamp() {
+        let fib: Vec<_> = FibonacciBuilder::default()
+            .with_max_times(5)
+            .with_max_delay(Duration::from_millis(2500))
+            .build()
+            .collect();
+
+        // The next delay of 3s is capped at the maximum delay.
+        assert_eq!(
+            fib,
+            [
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+                Duration::from_secs(2),
+                Duration::from_millis(2500),
+                Duration::from_millis(2500),
+            ]
+        );
+    }
+
     #[test]
     fn test_fibonacci_no_max_delay() {
         let mut fib = FibonacciBuilder::default()
@@ -276,6 +309,37 @@ mod tests {
         assert_eq!(None, fib.next());
     }
 
+    #[test]
+    fn test_fibonacci_total_delay() {
+        let mut fib = FibonacciBuilder::default()
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(7))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), fib.next());
+        assert_eq!(Some(Duration::from_secs(1)), fib.next());
+        assert_eq!(Some(Duration::from_secs(2)), fib.next());
+        assert_eq!(Some(Duration::from_secs(3)), fib.next());
+        // 1 + 1 + 2 + 3 + 5 = 12 > 7
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    fn test_fibonacci_total_delay_fused() {
+        let mut it = FibonacciBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .with_max_delay(Duration::from_secs(1))
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(5))
+            .build();
+
+        while it.next().is_some() {}
+        // A later delay could fit into the total delay again, but the backoff must stay exhausted.
+        for _ in 0..100 {
+            assert_eq!(None, it.next());
+        }
+    }
+
     #[test]
     fn test_fibonacci_max_times() {
         let mut fib = FibonacciBuilder::default().with_max_times(6).build();
@@ -0,0 +1,159 @@
+use core::time::Duration;
+
+/// JitterMode decides how a random jitter is applied to the delays of a backoff.
+///
+/// Jitter spreads out the retries of clients that fail at the same time, which
+/// prevents them from hammering the service in lockstep (the thundering herd problem).
+///
+/// # Default
+///
+/// [`JitterMode::None`]
+///
+/// # Randomness
+///
+/// The jitter is randomly seeded when the `std` feature is enabled, which is implied by the
+/// `tokio-sleep`, `gloo-timers-sleep` and `std-blocking-sleep` features. Without `std`, there is
+/// no source of entropy, so a seed must be provided via `with_jitter_seed` on the builder,
+/// for example from a hardware random number generator, or a seeded generator via `with_rng`.
+/// Otherwise, every process yields the same sequence of jittered delays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum JitterMode {
+    /// No jitter, the delay is used as is.
+    #[default]
+    None,
+    /// Add a random jitter within `(0, min_delay)` to the delay.
+    ///
+    /// For backoffs without a minimum delay, which are [`ScheduleBuilder`][crate::ScheduleBuilder] and
+    /// [`BackoffExt::with_added_jitter`][crate::BackoffExt::with_added_jitter], the jitter is within
+    /// `(0, delay)` instead.
+    Additive,
+    /// Use a random delay within `(0, delay)`.
+    Full,
+    /// Use a random delay within `(delay / 2, delay)`.
+    Equal,
+}
+
+impl JitterMode {
+    /// Apply the jitter to the given delay.
+    ///
+    /// `min_delay` is the minimum delay of the backoff, which bounds the [`JitterMode::Additive`] jitter.
+    pub(crate) fn apply(
+        self,
+        delay: Duration,
+        min_delay: Duration,
+        rng: &mut fastrand::Rng,
+    ) -> Duration {
+        match self {
+            JitterMode::None => delay,
+            JitterMode::Additive => delay.saturating_add(min_delay.mul_f32(rng.f32())),
+            JitterMode::Full => delay.mul_f32(rng.f32()),
+            JitterMode::Equal => {
+                let half = delay / 2;
+                half.saturating_add((delay - half).mul_f32(rng.f32()))
+            }
+        }
+    }
+}
+
+/// The seed used for jitter when neither a seed is provided nor `std` is enabled.
+#[cfg(not(feature = "std"))]
+const DEFAULT_JITTER_SEED: u64 = 0x4d59_5df4_d0f3_3173;
+
+/// Create the random number generator used by a backoff for jitter.
+///
+/// Without a seed, a randomly seeded generator is returned if `std` is enabled. Otherwise,
+/// there is no entropy available and a fixed seed will be used, see the docs of [`JitterMode`].
+pub(crate) fn jitter_rng(seed: Option<u64>) -> fastrand::Rng {
+    match seed {
+        Some(seed) => fastrand::Rng::with_seed(seed),
+        #[cfg(feature = "std")]
+        None => fastrand::Rng::new(),
+        #[cfg(not(feature = "std"))]
+        None => fastrand::Rng::with_seed(DEFAULT_JITTER_SEED),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::jitter_rng;
+    use super::JitterMode;
+
+    #[test]
+    fn test_jitter_none() {
+        let mut rng = jitter_rng(None);
+        let v = JitterMode::None.apply(Duration::from_secs(4), Duration::from_secs(1), &mut rng);
+        assert_eq!(Duration::from_secs(4), v);
+    }
+
+    #[test]
+    fn test_jitter_additive() {
+        let mut rng = jitter_rng(None);
+        for _ in 0..1_000 {
+            let v = JitterMode::Additive.apply(
+                Duration::from_secs(4),
+                Duration::from_secs(1),
+                &mut rng,
+            );
+            assert!(v >= Duration::from_secs(4), "current: {v:?}");
+            assert!(v < Duration::from_secs(5), "current: {v:?}");
+        }
+    }
+
+    #[test]
+    fn test_jitter_full() {
+        let mut rng = jitter_rng(None);
+        for _ in 0..1_000 {
+            let v =
+                JitterMode::Full.apply(Duration::from_secs(4), Duration::from_secs(1), &mut rng);
+            assert!(v < Duration::from_secs(4), "current: {v:?}");
+        }
+    }
+
+    #[test]
+    fn test_jitter_equal() {
+        let mut rng = jitter_rng(None);
+        for _ in 0..1_000 {
+            let v =
+                JitterMode::Equal.apply(Duration::from_secs(4), Duration::from_secs(1), &mut rng);
+            assert!(v >= Duration::from_secs(2), "current: {v:?}");
+            assert!(v < Duration::from_secs(4), "current: {v:?}");
+        }
+    }
+
+    #[test]
+    fn test_jitter_seed() {
+        let mut rng1 = jitter_rng(Some(42));
+        let mut rng2 = jitter_rng(Some(42));
+
+        for mode in [JitterMode::Additive, JitterMode::Full, JitterMode::Equal] {
+            for _ in 0..100 {
+                assert_eq!(
+                    mode.apply(Duration::from_secs(4), Duration::from_secs(1), &mut rng1),
+                    mode.apply(Duration::from_secs(4), Duration::from_secs(1), &mut rng2),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_jitter_saturating() {
+        let mut rng = jitter_rng(None);
+        let v = JitterMode::Additive.apply(Duration::MAX, Duration::from_secs(1), &mut rng);
+        assert!(
+            v >= Duration::MAX - Duration::from_secs(1),
+            "current: {v:?}"
+        );
+        let v = JitterMode::Equal.apply(Duration::MAX, Duration::from_secs(1), &mut rng);
+        assert!(v >= Duration::MAX / 2, "current: {v:?}");
+    }
+}
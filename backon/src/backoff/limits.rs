@@ -0,0 +1,285 @@
+use core::time::Duration;
+
+use crate::backoff::jitter_rng;
+use crate::backoff::JitterMode;
+
+/// TotalDelay stops a backoff once the sum of its delays would exceed the total delay.
+///
+/// Once stopped, it stays stopped even if a later delay would fit into the total delay again.
+#[derive(Debug, Clone)]
+pub(crate) struct TotalDelay {
+    total: Option<Duration>,
+    cumulative: Duration,
+    exhausted: bool,
+}
+
+impl TotalDelay {
+    pub(crate) fn new(total: Option<Duration>) -> Self {
+        Self {
+            total,
+            cumulative: Duration::ZERO,
+            exhausted: false,
+        }
+    }
+
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Count the delay towards the total, return `None` if the total delay would be exceeded.
+    pub(crate) fn take(&mut self, delay: Duration) -> Option<Duration> {
+        if self.exhausted {
+            return None;
+        }
+        let cumulative = self.cumulative.saturating_add(delay);
+        if let Some(total) = self.total {
+            if cumulative > total {
+                self.exhausted = true;
+                return None;
+            }
+        }
+        self.cumulative = cumulative;
+        Some(delay)
+    }
+}
+
+/// DelayLimits holds the jitter and the limits shared by the backoffs of the builders.
+///
+/// A backoff only computes its next delay, which is then capped at the maximum delay, jittered and
+/// counted towards the total delay here.
+#[derive(Debug)]
+pub(crate) struct DelayLimits {
+    jitter: JitterMode,
+    rng: fastrand::Rng,
+    min_delay: Duration,
+    max_delay: Option<Duration>,
+    max_times: Option<usize>,
+    total_delay: TotalDelay,
+    attempts: usize,
+}
+
+impl DelayLimits {
+    pub(crate) fn new(
+        jitter: JitterMode,
+        seed: Option<u64>,
+        min_delay: Duration,
+        max_delay: Option<Duration>,
+        max_times: Option<usize>,
+        total_delay: Option<Duration>,
+    ) -> Self {
+        Self {
+            jitter,
+            rng: jitter_rng(seed),
+            min_delay,
+            max_delay,
+            max_times,
+            total_delay: TotalDelay::new(total_delay),
+            attempts: 0,
+        }
+    }
+
+    pub(crate) fn min_delay(&self) -> Duration {
+        self.min_delay
+    }
+
+    pub(crate) fn max_delay(&self) -> Option<Duration> {
+        self.max_delay
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut fastrand::Rng {
+        &mut self.rng
+    }
+
+    /// Start the next attempt, return the number of attempts before it.
+    ///
+    /// Return `None` once the maximum number of attempts or the total delay has been reached.
+    pub(crate) fn next_attempt(&mut self) -> Option<usize> {
+        if self.total_delay.is_exhausted() || self.attempts >= self.max_times.unwrap_or(usize::MAX)
+        {
+            return None;
+        }
+        self.attempts += 1;
+        Some(self.attempts - 1)
+    }
+
+    /// Cap the delay at the maximum delay and apply the jitter.
+    ///
+    /// Return `None` if the total delay would be exceeded.
+    pub(crate) fn delay(&mut self, delay: Duration) -> Option<Duration> {
+        let delay = match self.max_delay {
+            Some(max_delay) => delay.min(max_delay),
+            None => delay,
+        };
+        let delay = self.jitter.apply(delay, self.min_delay, &mut self.rng);
+        self.total_delay.take(delay)
+    }
+}
+
+/// Implement the setters of the options shared by the builders.
+///
+/// The builders keep these options in fields of the same names, so that they can still be (de)serialized
+/// as flat structs with the `serde` feature. The groups of options are:
+///
+/// - `jitter`: `jitter`
+/// - `delay`: `min_delay` and `max_delay`
+/// - `limits`: `max_times`, `total_delay` and `seed`
+macro_rules! builder_options {
+    ($builder:ty: $($group:ident),+) => {
+        $(builder_options!(@$group $builder);)+
+    };
+    (@jitter $builder:ty) => {
+        impl $builder {
+            /// Set the jitter for the backoff.
+            ///
+            /// When jitter is enabled, a random jitter within `(0, min_delay)` is added to the delay,
+            /// where `min_delay` is the first delay of the backoff.
+            ///
+            /// This is the same as `with_jitter_mode(JitterMode::Additive)`.
+            pub fn with_jitter(mut self) -> Self {
+                self.jitter = $crate::JitterMode::Additive;
+                self
+            }
+
+            /// Set the jitter mode for the backoff.
+            ///
+            /// See [`JitterMode`](crate::JitterMode) for how each mode is applied to the delay.
+            pub fn with_jitter_mode(mut self, mode: $crate::JitterMode) -> Self {
+                self.jitter = mode;
+                self
+            }
+        }
+    };
+    (@delay $builder:ty) => {
+        impl $builder {
+            /// Set the minimum delay for the backoff.
+            pub fn with_min_delay(mut self, min_delay: core::time::Duration) -> Self {
+                self.min_delay = min_delay;
+                self
+            }
+
+            /// Set the maximum delay for the backoff.
+            ///
+            /// The delay will not increase once it reaches the maximum delay, and is capped at it
+            /// before jitter is applied.
+            pub fn with_max_delay(mut self, max_delay: core::time::Duration) -> Self {
+                self.max_delay = Some(max_delay);
+                self
+            }
+
+            /// Set no maximum delay for the backoff.
+            ///
+            /// The delay will keep increasing.
+            ///
+            /// _The delay will saturate at `Duration::MAX` which is an **unrealistic** delay._
+            pub fn without_max_delay(mut self) -> Self {
+                self.max_delay = None;
+                self
+            }
+        }
+    };
+    (@limits $builder:ty) => {
+        impl $builder {
+            /// Set the maximum number of attempts for the current backoff.
+            ///
+            /// The backoff will stop if the maximum number of attempts is reached.
+            pub fn with_max_times(mut self, max_times: usize) -> Self {
+                self.max_times = Some(max_times);
+                self
+            }
+
+            /// Set no maximum number of attempts for the current backoff.
+            ///
+            /// The backoff will not stop by itself.
+            ///
+            /// _The backoff could stop reaching `usize::MAX` attempts but this is **unrealistic**._
+            pub fn without_max_times(mut self) -> Self {
+                self.max_times = None;
+                self
+            }
+
+            /// Set the total delay for the backoff.
+            ///
+            /// The backoff will stop once the sum of all returned delays would exceed the total delay.
+            pub fn with_total_delay(mut self, total_delay: core::time::Duration) -> Self {
+                self.total_delay = Some(total_delay);
+                self
+            }
+
+            /// Set no total delay for the backoff.
+            ///
+            /// The backoff will not stop by the sum of its delays.
+            pub fn without_total_delay(mut self) -> Self {
+                self.total_delay = None;
+                self
+            }
+
+            /// Set the seed of the random number generator used for jitter.
+            ///
+            /// Backoffs built with the same seed will always yield the same sequence of delays,
+            /// which is useful to make tests reproducible.
+            ///
+            /// If not specified, a random seed will be used if `std` is enabled. Without `std`, the seed
+            /// must be set for the jitter to differ between processes, see [`JitterMode`](crate::JitterMode).
+            pub fn with_jitter_seed(mut self, seed: u64) -> Self {
+                self.seed = Some(seed);
+                self
+            }
+
+            /// Set the random number generator used for jitter.
+            ///
+            /// The builder keeps the state of the generator, and every backoff built from it continues from
+            /// that state. This allows to provide a generator seeded from a source of entropy without `std`,
+            /// or a seeded generator to make tests reproducible.
+            ///
+            /// This replaces the seed set by `with_jitter_seed`.
+            pub fn with_rng(mut self, rng: fastrand::Rng) -> Self {
+                self.seed = Some(rng.get_seed());
+                self
+            }
+        }
+    };
+}
+pub(crate) use builder_options;
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn test_total_delay() {
+        let mut total = TotalDelay::new(Some(Duration::from_secs(3)));
+
+        assert_eq!(
+            Some(Duration::from_secs(2)),
+            total.take(Duration::from_secs(2))
+        );
+        assert_eq!(None, total.take(Duration::from_secs(2)));
+        // The second delay would fit, but the total delay has been exhausted already.
+        assert_eq!(None, total.take(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_delay_limits() {
+        let mut limits = DelayLimits::new(
+            JitterMode::None,
+            None,
+            Duration::from_secs(1),
+            Some(Duration::from_secs(5)),
+            Some(2),
+            None,
+        );
+
+        assert_eq!(Some(0), limits.next_attempt());
+        assert_eq!(
+            Some(Duration::from_secs(5)),
+            limits.delay(Duration::from_secs(10))
+        );
+        assert_eq!(Some(1), limits.next_attempt());
+        assert_eq!(None, limits.next_attempt());
+    }
+}
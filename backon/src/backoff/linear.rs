@@ -0,0 +1,276 @@
+use core::time::Duration;
+
+use crate::backoff::builder_options;
+use crate::backoff::BackoffBuilder;
+use crate::backoff::DelayLimits;
+use crate::backoff::JitterMode;
+
+/// LinearBuilder is used to build a [`LinearBackoff`] which offers a delay growing by a fixed step with every retry.
+///
+/// # Default
+///
+/// - jitter: [`JitterMode::None`]
+/// - min_delay: 1s
+/// - step: 1s
+/// - max_delay: 60s
+/// - max_times: 3
+/// - total_delay: None
+/// - jitter_seed: random
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::LinearBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch.retry(LinearBuilder::default()).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct LinearBuilder {
+    jitter: JitterMode,
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration"))]
+    min_delay: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration"))]
+    step: Duration,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    max_delay: Option<Duration>,
+    max_times: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    total_delay: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(rename = "jitter_seed"))]
+    seed: Option<u64>,
+}
+
+impl Default for LinearBuilder {
+    fn default() -> Self {
+        Self {
+            jitter: JitterMode::None,
+            min_delay: Duration::from_secs(1),
+            step: Duration::from_secs(1),
+            max_delay: Some(Duration::from_secs(60)),
+            max_times: Some(3),
+            total_delay: None,
+            seed: None,
+        }
+    }
+}
+
+impl LinearBuilder {
+    /// Set the step added to the delay for every retry.
+    ///
+    /// The n-th delay is `min_delay + (n - 1) * step`.
+    pub fn with_step(mut self, step: Duration) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+builder_options!(LinearBuilder: jitter, delay, limits);
+
+impl BackoffBuilder for LinearBuilder {
+    type Backoff = LinearBackoff;
+
+    fn build(self) -> Self::Backoff {
+        LinearBackoff {
+            step: self.step,
+            limits: DelayLimits::new(
+                self.jitter,
+                self.seed,
+                self.min_delay,
+                self.max_delay,
+                self.max_times,
+                self.total_delay,
+            ),
+        }
+    }
+}
+
+impl BackoffBuilder for &LinearBuilder {
+    type Backoff = LinearBackoff;
+
+    fn build(self) -> Self::Backoff {
+        (*self).build()
+    }
+}
+
+/// LinearBackoff offers a delay growing by a fixed step with every retry.
+///
+/// This backoff strategy is constructed by [`LinearBuilder`].
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct LinearBackoff {
+    step: Duration,
+    limits: DelayLimits,
+}
+
+impl Iterator for LinearBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let attempts = self.limits.next_attempt()?;
+        let steps = u32::try_from(attempts).unwrap_or(u32::MAX);
+
+        let next = self
+            .limits
+            .min_delay()
+            .saturating_add(self.step.saturating_mul(steps));
+        self.limits.delay(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use crate::BackoffBuilder;
+    use crate::JitterMode;
+    use crate::LinearBuilder;
+
+    #[test]
+    fn test_linear_default() {
+        let mut linear = LinearBuilder::default().build();
+
+        assert_eq!(Some(Duration::from_secs(1)), linear.next());
+        assert_eq!(Some(Duration::from_secs(2)), linear.next());
+        assert_eq!(Some(Duration::from_secs(3)), linear.next());
+        assert_eq!(None, linear.next());
+    }
+
+    #[test]
+    fn test_linear_step() {
+        let mut linear = LinearBuilder::default()
+            .with_min_delay(Duration::from_millis(100))
+            .with_step(Duration::from_millis(250))
+            .build();
+
+        assert_eq!(Some(Duration::from_millis(100)), linear.next());
+        assert_eq!(Some(Duration::from_millis(350)), linear.next());
+        assert_eq!(Some(Duration::from_millis(600)), linear.next());
+        assert_eq!(None, linear.next());
+    }
+
+    #[test]
+    fn test_linear_jitter() {
+        let mut linear = LinearBuilder::default().with_jitter().build();
+
+        let v = linear.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(1), "current: {v:?}");
+        assert!(v < Duration::from_secs(2), "current: {v:?}");
+
+        let v = linear.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(2), "current: {v:?}");
+        assert!(v < Duration::from_secs(3), "current: {v:?}");
+    }
+
+    #[test]
+    fn test_linear_equal_jitter() {
+        let mut linear = LinearBuilder::default()
+            .with_jitter_mode(JitterMode::Equal)
+            .build();
+
+        let v = linear.next().expect("value must valid");
+        assert!(v >= Duration::from_millis(500), "current: {v:?}");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = linear.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(1), "current: {v:?}");
+        assert!(v < Duration::from_secs(2), "current: {v:?}");
+    }
+
+    #[test]
+    fn test_linear_jitter_seed() {
+        let builder = LinearBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(42)
+            .without_max_times();
+
+        let linear1: Vec<_> = builder.build().take(100).collect();
+        let linear2: Vec<_> = builder.build().take(100).collect();
+        assert_eq!(linear1, linear2);
+    }
+
+    #[test]
+    fn test_linear_max_delay() {
+        let mut linear = LinearBuilder::default()
+            .with_max_times(4)
+            .with_max_delay(Duration::from_secs(2))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), linear.next());
+        assert_eq!(Some(Duration::from_secs(2)), linear.next());
+        assert_eq!(Some(Duration::from_secs(2)), linear.next());
+        assert_eq!(Some(Duration::from_secs(2)), linear.next());
+        assert_eq!(None, linear.next());
+    }
+
+    #[test]
+    fn test_linear_no_max_delay() {
+        let mut linear = LinearBuilder::default()
+            .with_max_times(2)
+            .with_min_delay(Duration::MAX)
+            .without_max_delay()
+            .build();
+
+        assert_eq!(Some(Duration::MAX), linear.next());
+        assert_eq!(Some(Duration::MAX), linear.next());
+        assert_eq!(None, linear.next());
+    }
+
+    #[test]
+    fn test_linear_total_delay() {
+        let mut linear = LinearBuilder::default()
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(7))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), linear.next());
+        assert_eq!(Some(Duration::from_secs(2)), linear.next());
+        assert_eq!(Some(Duration::from_secs(3)), linear.next());
+        // 1 + 2 + 3 + 4 = 10 > 7
+        assert_eq!(None, linear.next());
+    }
+
+    #[test]
+    fn test_linear_total_delay_fused() {
+        let mut it = LinearBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(5))
+            .build();
+
+        while it.next().is_some() {}
+        // A later delay could fit into the total delay again, but the backoff must stay exhausted.
+        for _ in 0..100 {
+            assert_eq!(None, it.next());
+        }
+    }
+}
@@ -1,6 +1,27 @@
 mod api;
 pub use api::*;
 
+mod any;
+pub use any::AnyBackoff;
+pub use any::AnyBackoffBuilder;
+
+mod ext;
+pub use ext::AddedJitter;
+pub use ext::BackoffExt;
+pub use ext::MaxDelay;
+pub use ext::MinDelay;
+pub use ext::Scale;
+pub use ext::TakeTotal;
+
+mod jitter;
+pub(crate) use jitter::jitter_rng;
+pub use jitter::JitterMode;
+
+mod limits;
+pub(crate) use limits::builder_options;
+pub(crate) use limits::DelayLimits;
+pub(crate) use limits::TotalDelay;
+
 mod constant;
 pub use constant::ConstantBackoff;
 pub use constant::ConstantBuilder;
@@ -9,6 +30,26 @@ mod fibonacci;
 pub use fibonacci::FibonacciBackoff;
 pub use fibonacci::FibonacciBuilder;
 
+mod linear;
+pub use linear::LinearBackoff;
+pub use linear::LinearBuilder;
+
+mod polynomial;
+pub use polynomial::PolynomialBackoff;
+pub use polynomial::PolynomialBuilder;
+
+mod schedule;
+pub use schedule::ParseScheduleError;
+pub use schedule::ScheduleBackoff;
+pub use schedule::ScheduleBuilder;
+
+#[cfg(feature = "serde")]
+mod serde_duration;
+
 mod exponential;
 pub use exponential::ExponentialBackoff;
 pub use exponential::ExponentialBuilder;
+
+mod decorrelated_jitter;
+pub use decorrelated_jitter::DecorrelatedJitterBackoff;
+pub use decorrelated_jitter::DecorrelatedJitterBuilder;
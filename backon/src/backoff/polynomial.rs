@@ -0,0 +1,281 @@
+use core::time::Duration;
+
+use crate::backoff::builder_options;
+use crate::backoff::BackoffBuilder;
+use crate::backoff::DelayLimits;
+use crate::backoff::JitterMode;
+
+/// PolynomialBuilder is used to build a [`PolynomialBackoff`] which offers a delay growing polynomially with the number of retries.
+///
+/// # Default
+///
+/// - jitter: [`JitterMode::None`]
+/// - min_delay: 1s
+/// - degree: 2
+/// - max_delay: 60s
+/// - max_times: 3
+/// - total_delay: None
+/// - jitter_seed: random
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::PolynomialBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch.retry(PolynomialBuilder::default()).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct PolynomialBuilder {
+    jitter: JitterMode,
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration"))]
+    min_delay: Duration,
+    degree: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    max_delay: Option<Duration>,
+    max_times: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::backoff::serde_duration::option")
+    )]
+    total_delay: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(rename = "jitter_seed"))]
+    seed: Option<u64>,
+}
+
+impl Default for PolynomialBuilder {
+    fn default() -> Self {
+        Self {
+            jitter: JitterMode::None,
+            min_delay: Duration::from_secs(1),
+            degree: 2,
+            max_delay: Some(Duration::from_secs(60)),
+            max_times: Some(3),
+            total_delay: None,
+            seed: None,
+        }
+    }
+}
+
+impl PolynomialBuilder {
+    /// Set the degree of the polynomial.
+    ///
+    /// The n-th delay is `min_delay * n^degree`.
+    pub fn with_degree(mut self, degree: u32) -> Self {
+        self.degree = degree;
+        self
+    }
+}
+
+builder_options!(PolynomialBuilder: jitter, delay, limits);
+
+impl BackoffBuilder for PolynomialBuilder {
+    type Backoff = PolynomialBackoff;
+
+    fn build(self) -> Self::Backoff {
+        PolynomialBackoff {
+            degree: self.degree,
+            limits: DelayLimits::new(
+                self.jitter,
+                self.seed,
+                self.min_delay,
+                self.max_delay,
+                self.max_times,
+                self.total_delay,
+            ),
+        }
+    }
+}
+
+impl BackoffBuilder for &PolynomialBuilder {
+    type Backoff = PolynomialBackoff;
+
+    fn build(self) -> Self::Backoff {
+        (*self).build()
+    }
+}
+
+/// PolynomialBackoff offers a delay growing polynomially with the number of retries.
+///
+/// This backoff strategy is constructed by [`PolynomialBuilder`].
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct PolynomialBackoff {
+    degree: u32,
+    limits: DelayLimits,
+}
+
+impl Iterator for PolynomialBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let attempts = self.limits.next_attempt()? + 1;
+
+        let min_delay = self.limits.min_delay();
+        let factor = u32::try_from(attempts)
+            .ok()
+            .and_then(|n| n.checked_pow(self.degree));
+        let next = match factor {
+            Some(factor) => min_delay.saturating_mul(factor),
+            None if min_delay.is_zero() => Duration::ZERO,
+            None => Duration::MAX,
+        };
+        self.limits.delay(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use crate::BackoffBuilder;
+    use crate::JitterMode;
+    use crate::PolynomialBuilder;
+
+    #[test]
+    fn test_polynomial_default() {
+        let mut poly = PolynomialBuilder::default().build();
+
+        assert_eq!(Some(Duration::from_secs(1)), poly.next());
+        assert_eq!(Some(Duration::from_secs(4)), poly.next());
+        assert_eq!(Some(Duration::from_secs(9)), poly.next());
+        assert_eq!(None, poly.next());
+    }
+
+    #[test]
+    fn test_polynomial_degree() {
+        let mut poly = PolynomialBuilder::default()
+            .with_min_delay(Duration::from_millis(100))
+            .with_degree(3)
+            .build();
+
+        assert_eq!(Some(Duration::from_millis(100)), poly.next());
+        assert_eq!(Some(Duration::from_millis(800)), poly.next());
+        assert_eq!(Some(Duration::from_millis(2700)), poly.next());
+        assert_eq!(None, poly.next());
+    }
+
+    #[test]
+    fn test_polynomial_jitter() {
+        let mut poly = PolynomialBuilder::default().with_jitter().build();
+
+        let v = poly.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(1), "current: {v:?}");
+        assert!(v < Duration::from_secs(2), "current: {v:?}");
+
+        let v = poly.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(4), "current: {v:?}");
+        assert!(v < Duration::from_secs(5), "current: {v:?}");
+    }
+
+    #[test]
+    fn test_polynomial_full_jitter() {
+        let mut poly = PolynomialBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .build();
+
+        let v = poly.next().expect("value must valid");
+        assert!(v < Duration::from_secs(1), "current: {v:?}");
+
+        let v = poly.next().expect("value must valid");
+        assert!(v < Duration::from_secs(4), "current: {v:?}");
+    }
+
+    #[test]
+    fn test_polynomial_jitter_seed() {
+        let builder = PolynomialBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(42)
+            .without_max_times();
+
+        let poly1: Vec<_> = builder.build().take(100).collect();
+        let poly2: Vec<_> = builder.build().take(100).collect();
+        assert_eq!(poly1, poly2);
+    }
+
+    #[test]
+    fn test_polynomial_max_delay() {
+        let mut poly = PolynomialBuilder::default()
+            .with_max_times(4)
+            .with_max_delay(Duration::from_secs(5))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), poly.next());
+        assert_eq!(Some(Duration::from_secs(4)), poly.next());
+        assert_eq!(Some(Duration::from_secs(5)), poly.next());
+        assert_eq!(Some(Duration::from_secs(5)), poly.next());
+        assert_eq!(None, poly.next());
+    }
+
+    #[test]
+    fn test_polynomial_no_max_delay() {
+        let mut poly = PolynomialBuilder::default()
+            .with_max_times(3)
+            .with_min_delay(Duration::from_secs(10_000_000_000_000_000_000))
+            .without_max_delay()
+            .build();
+
+        assert_eq!(
+            Some(Duration::from_secs(10_000_000_000_000_000_000)),
+            poly.next()
+        );
+        assert_eq!(Some(Duration::MAX), poly.next());
+        assert_eq!(Some(Duration::MAX), poly.next());
+        assert_eq!(None, poly.next());
+    }
+
+    #[test]
+    fn test_polynomial_total_delay() {
+        let mut poly = PolynomialBuilder::default()
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(20))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), poly.next());
+        assert_eq!(Some(Duration::from_secs(4)), poly.next());
+        assert_eq!(Some(Duration::from_secs(9)), poly.next());
+        // 1 + 4 + 9 + 16 = 30 > 20
+        assert_eq!(None, poly.next());
+    }
+
+    #[test]
+    fn test_polynomial_total_delay_fused() {
+        let mut it = PolynomialBuilder::default()
+            .with_jitter_mode(JitterMode::Full)
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(5))
+            .build();
+
+        while it.next().is_some() {}
+        // A later delay could fit into the total delay again, but the backoff must stay exhausted.
+        for _ in 0..100 {
+            assert_eq!(None, it.next());
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+use crate::backoff::jitter_rng;
+use crate::backoff::BackoffBuilder;
+use crate::backoff::JitterMode;
+
+/// ScheduleBuilder is used to build a [`ScheduleBackoff`] which yields exactly the given delays.
+///
+/// A schedule can be parsed from a human-readable string, which makes it easy to configure retries in
+/// config files. Delays are separated by `,`, and a delay can be repeated with `*`, for example
+/// `100ms,500ms,2s,5s*3`. The supported units are `ns`, `us`, `ms`, `s`, `m` and `h`, and they can be
+/// combined like `1m30s`. A parsed schedule holds at most 10000 delays.
+///
+/// Formatting a schedule with [`Display`][fmt::Display] gives the same format back. Only the delays are
+/// formatted, so the jitter settings are not kept, and an empty schedule is formatted as an empty string
+/// which can't be parsed.
+///
+/// # Default
+///
+/// - delays: empty
+/// - jitter: [`JitterMode::None`]
+/// - jitter_seed: random
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::Retryable;
+/// use backon::ScheduleBuilder;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let schedule: ScheduleBuilder = "100ms,500ms,2s,5s*3".parse()?;
+///     let content = fetch.retry(&schedule).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct ScheduleBuilder {
+    #[cfg_attr(feature = "serde", serde(with = "crate::backoff::serde_duration::vec"))]
+    delays: Vec<Duration>,
+    jitter: JitterMode,
+    #[cfg_attr(feature = "serde", serde(rename = "jitter_seed"))]
+    seed: Option<u64>,
+}
+
+impl ScheduleBuilder {
+    /// Create a schedule yielding exactly the given delays.
+    pub fn from_delays(delays: impl IntoIterator<Item = Duration>) -> Self {
+        Self {
+            delays: delays.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Return the delays of the schedule.
+    pub fn delays(&self) -> &[Duration] {
+        &self.delays
+    }
+
+    /// Set jitter for the backoff.
+    ///
+    /// This is the same as `with_jitter_mode(JitterMode::Additive)`, the jitter is within `(0, delay)`.
+    pub fn with_jitter(mut self) -> Self {
+        self.jitter = JitterMode::Additive;
+        self
+    }
+
+    /// Set the jitter mode for the backoff.
+    ///
+    /// See [`JitterMode`] for how each mode is applied to the delay.
+    pub fn with_jitter_mode(mut self, mode: JitterMode) -> Self {
+        self.jitter = mode;
+        self
+    }
+
+    /// Set the seed of the random number generator used for jitter.
+    ///
+    /// Backoffs built with the same seed will always yield the same sequence of delays,
+    /// which is useful to make tests reproducible.
+    ///
+    /// If not specified, a random seed will be used if `std` is enabled. Without `std`, the seed
+    /// must be set for the jitter to differ between processes, see [`JitterMode`].
+    pub fn with_jitter_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the random number generator used for jitter.
+    ///
+    /// The builder keeps the state of the generator, and every backoff built from it continues from
+    /// that state. This allows to provide a generator seeded from a source of entropy without `std`,
+    /// or a seeded generator to make tests reproducible.
+    ///
+    /// This replaces the seed set by `with_jitter_seed`.
+    pub fn with_rng(mut self, rng: fastrand::Rng) -> Self {
+        self.seed = Some(rng.get_seed());
+        self
+    }
+}
+
+impl BackoffBuilder for ScheduleBuilder {
+    type Backoff = ScheduleBackoff;
+
+    fn build(self) -> Self::Backoff {
+        ScheduleBackoff {
+            delays: self.delays.into_iter(),
+            jitter: self.jitter,
+            rng: jitter_rng(self.seed),
+        }
+    }
+}
+
+impl BackoffBuilder for &ScheduleBuilder {
+    type Backoff = ScheduleBackoff;
+
+    fn build(self) -> Self::Backoff {
+        self.clone().build()
+    }
+}
+
+/// The maximum number of delays in a parsed schedule, so that a repeat count from a config can't exhaust memory.
+const MAX_PARSED_DELAYS: usize = 10_000;
+
+impl FromStr for ScheduleBuilder {
+    type Err = ParseScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut delays = Vec::new();
+        for item in s.split(',') {
+            let (delay, times) = match item.split_once('*') {
+                Some((delay, times)) => {
+                    let times = times
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| ParseScheduleError::new(s, "invalid repeat count"))?;
+                    (delay, times)
+                }
+                None => (item, 1),
+            };
+            if times == 0 {
+                return Err(ParseScheduleError::new(s, "repeat count must be positive"));
+            }
+
+            if times > MAX_PARSED_DELAYS - delays.len() {
+                return Err(ParseScheduleError::new(s, "too many delays"));
+            }
+
+            let delay = parse_duration(delay).map_err(|err| ParseScheduleError::new(s, err))?;
+            delays.extend(core::iter::repeat(delay).take(times));
+        }
+
+        Ok(Self::from_delays(delays))
+    }
+}
+
+impl fmt::Display for ScheduleBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut delays = self.delays.iter().peekable();
+        let mut first = true;
+        while let Some(delay) = delays.next() {
+            let mut times = 1;
+            while delays.next_if_eq(&delay).is_some() {
+                times += 1;
+            }
+
+            if !first {
+                f.write_str(",")?;
+            }
+            first = false;
+            f.write_str(&format_duration(*delay))?;
+            if times > 1 {
+                write!(f, "*{times}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ScheduleBackoff yields exactly the delays of a schedule.
+///
+/// This backoff strategy is constructed by [`ScheduleBuilder`].
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct ScheduleBackoff {
+    delays: alloc::vec::IntoIter<Duration>,
+    jitter: JitterMode,
+    rng: fastrand::Rng,
+}
+
+impl Iterator for ScheduleBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let delay = self.delays.next()?;
+        Some(self.jitter.apply(delay, delay, &mut self.rng))
+    }
+}
+
+/// ParseScheduleError is the error returned when a schedule or a duration can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseScheduleError {
+    input: String,
+    reason: &'static str,
+}
+
+impl ParseScheduleError {
+    fn new(input: &str, reason: &'static str) -> Self {
+        Self {
+            input: input.to_string(),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for ParseScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid schedule `{}`: {}", self.input, self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseScheduleError {}
+
+/// Parse a duration like `1m30s` or `100ms`, return the reason if it's invalid.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, &'static str> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty duration");
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err("expected a number");
+        }
+        let value = rest[..digits]
+            .parse::<u64>()
+            .map_err(|_| "number is too large")?;
+        rest = rest[digits..].trim_start();
+
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let delay = match &rest[..unit] {
+            "ns" => Some(Duration::from_nanos(value)),
+            "us" | "µs" => Some(Duration::from_micros(value)),
+            "ms" => Some(Duration::from_millis(value)),
+            "s" => Some(Duration::from_secs(value)),
+            "m" => value.checked_mul(60).map(Duration::from_secs),
+            "h" => value.checked_mul(60 * 60).map(Duration::from_secs),
+            "" => return Err("missing unit"),
+            _ => return Err("unknown unit, expected one of `ns`, `us`, `ms`, `s`, `m` or `h`"),
+        };
+        rest = rest[unit..].trim_start();
+
+        total = delay
+            .and_then(|delay| total.checked_add(delay))
+            .ok_or("duration is too large")?;
+    }
+    Ok(total)
+}
+
+/// Format a duration like `1m30s` or `100ms`, which can be parsed by [`parse_duration`].
+pub(crate) fn format_duration(dur: Duration) -> String {
+    if dur.is_zero() {
+        return "0s".to_string();
+    }
+
+    let secs = dur.as_secs();
+    let nanos = dur.subsec_nanos();
+    let parts = [
+        (secs / 3600, "h"),
+        (secs / 60 % 60, "m"),
+        (secs % 60, "s"),
+        (u64::from(nanos / 1_000_000), "ms"),
+        (u64::from(nanos / 1_000 % 1_000), "us"),
+        (u64::from(nanos % 1_000), "ns"),
+    ];
+
+    let mut s = String::new();
+    for (value, unit) in parts {
+        if value > 0 {
+            s.push_str(&value.to_string());
+            s.push_str(unit);
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn test_schedule_from_delays() {
+        let builder =
+            ScheduleBuilder::from_delays([Duration::from_millis(100), Duration::from_secs(1)]);
+
+        // The builder can be used many times by reference.
+        for _ in 0..2 {
+            let delays: Vec<_> = (&builder).build().collect();
+            assert_eq!(
+                delays,
+                vec![Duration::from_millis(100), Duration::from_secs(1)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_schedule_jitter() {
+        let builder = ScheduleBuilder::from_delays([Duration::from_secs(4); 100])
+            .with_jitter_mode(JitterMode::Equal);
+
+        for v in builder.build() {
+            assert!(v >= Duration::from_secs(2), "current: {v:?}");
+            assert!(v < Duration::from_secs(4), "current: {v:?}");
+        }
+    }
+
+    #[test]
+    fn test_schedule_parse() {
+        let builder: ScheduleBuilder = "100ms, 500ms,2s,5s*3,1m30s".parse().unwrap();
+
+        assert_eq!(
+            builder.delays(),
+            [
+                Duration::from_millis(100),
+                Duration::from_millis(500),
+                Duration::from_secs(2),
+                Duration::from_secs(5),
+                Duration::from_secs(5),
+                Duration::from_secs(5),
+                Duration::from_secs(90),
+            ]
+        );
+        assert_eq!(builder.to_string(), "100ms,500ms,2s,5s*3,1m30s");
+        assert_eq!(builder.to_string().parse::<ScheduleBuilder>(), Ok(builder));
+
+        let builder: ScheduleBuilder = "1s*10000".parse().unwrap();
+        assert_eq!(builder.delays().len(), 10_000);
+    }
+
+    #[test]
+    fn test_schedule_parse_error() {
+        for (input, reason) in [
+            ("", "empty duration"),
+            ("1s,,2s", "empty duration"),
+            ("100", "missing unit"),
+            (
+                "1d",
+                "unknown unit, expected one of `ns`, `us`, `ms`, `s`, `m` or `h`",
+            ),
+            ("ms", "expected a number"),
+            ("1s*x", "invalid repeat count"),
+            ("1s*0", "repeat count must be positive"),
+            ("1s*18446744073709551615", "too many delays"),
+            ("1s*100000000000", "too many delays"),
+            ("1s*5000,2s*5001", "too many delays"),
+            ("18446744073709551615h", "duration is too large"),
+        ] {
+            let err = input.parse::<ScheduleBuilder>().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("invalid schedule `{input}`: {reason}")
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(
+            format_duration(Duration::from_nanos(1_001_001)),
+            "1ms1us1ns"
+        );
+        assert_eq!(format_duration(Duration::from_secs(3661)), "1h1m1s");
+
+        for dur in [
+            Duration::from_nanos(1),
+            Duration::from_secs(86400),
+            Duration::MAX,
+        ] {
+            assert_eq!(parse_duration(&format_duration(dur)), Ok(dur));
+        }
+    }
+}
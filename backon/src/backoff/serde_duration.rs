@@ -0,0 +1,193 @@
+//! (De)serialize durations as humantime-style strings like `1m30s` or `100ms`.
+//!
+//! Used by the `serde` feature via `#[serde(with = "...")]` on the fields of the builders.
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::backoff::schedule::format_duration;
+use crate::backoff::schedule::parse_duration;
+
+/// A duration in the humantime-style format.
+struct HumanDuration(Duration);
+
+impl Serialize for HumanDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_duration(self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = HumanDuration;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a duration like `1m30s` or `100ms`")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                parse_duration(v)
+                    .map(HumanDuration)
+                    .map_err(|reason| E::custom(format!("invalid duration `{v}`: {reason}")))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+pub(crate) fn serialize<S: Serializer>(dur: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    HumanDuration(*dur).serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    HumanDuration::deserialize(deserializer).map(|dur| dur.0)
+}
+
+/// `None` is (de)serialized as null, which disables the limit.
+pub(crate) mod option {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        dur: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        dur.map(HumanDuration).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<HumanDuration>::deserialize(deserializer).map(|dur| dur.map(|dur| dur.0))
+    }
+}
+
+pub(crate) mod vec {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        durs: &[Duration],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(durs.iter().copied().map(HumanDuration))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Duration>, D::Error> {
+        Vec::<HumanDuration>::deserialize(deserializer)
+            .map(|durs| durs.into_iter().map(|dur| dur.0).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    use serde_json::json;
+
+    use crate::BackoffBuilder;
+    use crate::ConstantBuilder;
+    use crate::ExponentialBuilder;
+    use crate::FibonacciBuilder;
+    use crate::JitterMode;
+    use crate::ScheduleBuilder;
+
+    #[test]
+    fn test_deserialize_defaults() {
+        let builder: ExponentialBuilder = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(
+            serde_json::to_value(builder).unwrap(),
+            serde_json::to_value(ExponentialBuilder::default()).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(FibonacciBuilder::default()).unwrap(),
+            json!({
+                "jitter": "none",
+                "min_delay": "1s",
+                "max_delay": "1m",
+                "max_times": 3,
+                "total_delay": null,
+                "jitter_seed": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_builder() {
+        let builder: ConstantBuilder = serde_json::from_value(json!({
+            "delay": "1m30s",
+            "max_times": null,
+            "total_delay": "5m",
+            "jitter": "equal",
+            "jitter_seed": 42,
+        }))
+        .unwrap();
+        let expected = ConstantBuilder::default()
+            .with_delay(Duration::from_secs(90))
+            .without_max_times()
+            .with_total_delay(Duration::from_secs(300))
+            .with_jitter_mode(JitterMode::Equal)
+            .with_jitter_seed(42);
+
+        assert_eq!(
+            builder.build().collect::<Vec<_>>(),
+            expected.build().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let builder = ScheduleBuilder::from_delays([
+            Duration::from_millis(100),
+            Duration::from_nanos(1_500_001),
+        ])
+        .with_jitter();
+        let value = serde_json::to_value(&builder).unwrap();
+        assert_eq!(value["delays"], json!(["100ms", "1ms500us1ns"]));
+        assert_eq!(
+            serde_json::from_value::<ScheduleBuilder>(value).unwrap(),
+            builder
+        );
+    }
+
+    #[test]
+    fn test_deserialize_error() {
+        let err =
+            serde_json::from_value::<ExponentialBuilder>(json!({ "min_delay": "1d" })).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid duration `1d`: unknown unit, expected one of `ns`, `us`, `ms`, `s`, `m` or `h`"
+        );
+
+        let err =
+            serde_json::from_value::<ExponentialBuilder>(json!({ "min_delay": 1 })).unwrap_err();
+        assert!(err.to_string().contains("a duration like"), "{err}");
+
+        let err =
+            serde_json::from_value::<ExponentialBuilder>(json!({ "factor": 0.5 })).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid factor `0.5`, expected a finite number no less than 1"
+        );
+
+        let err = serde_json::from_value::<ExponentialBuilder>(json!({ "max_delays": "1s" }))
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown field"), "{err}");
+    }
+}
@@ -1,8 +1,14 @@
+use core::fmt::Display;
 use core::time::Duration;
 
 use crate::backoff::BackoffBuilder;
 use crate::blocking_sleep::MaybeBlockingSleeper;
-use crate::{Backoff, BlockingSleeper, DefaultBlockingSleeper};
+use crate::clock::resolve_deadline;
+use crate::clock::MaybeClock;
+use crate::retry_state::{
+    CollectStats, RetryCondition, RetryNotify, RetryProgress, StatsOutput, WithState,
+};
+use crate::{Backoff, BlockingSleeper, Clock, DefaultBlockingSleeper, DefaultClock, RetryState};
 
 /// BlockingRetryable adds retry support for blocking functions.
 ///
@@ -66,12 +72,26 @@ pub struct BlockingRetry<
     SF: MaybeBlockingSleeper = DefaultBlockingSleeper,
     RF = fn(&E) -> bool,
     NF = fn(&E, Duration),
+    CF: MaybeClock = DefaultClock,
+    AF = fn(&E, Option<Duration>) -> Option<Duration>,
+    OF = fn(&T) -> bool,
+    ST = (),
 > {
     backoff: B,
     retryable: RF,
     notify: NF,
+    adjust: AF,
+    when_ok: OF,
     f: F,
     sleep_fn: SF,
+    clock: CF,
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    progress: RetryProgress,
+    /// Only read while the `tracing` feature is enabled.
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    trace_error: Option<fn(&E) -> &dyn Display>,
+    stats: ST,
 }
 
 impl<B, T, E, F> BlockingRetry<B, T, E, F>
@@ -85,19 +105,31 @@ where
             backoff,
             retryable: |_: &E| true,
             notify: |_: &E, _: Duration| {},
+            adjust: |_: &E, dur: Option<Duration>| dur,
+            when_ok: |_: &T| false,
             sleep_fn: DefaultBlockingSleeper::default(),
+            clock: DefaultClock::default(),
+            timeout: None,
+            deadline: None,
+            progress: RetryProgress::default(),
+            trace_error: None,
+            stats: (),
             f,
         }
     }
 }
 
-impl<B, T, E, F, SF, RF, NF> BlockingRetry<B, T, E, F, SF, RF, NF>
+#[allow(clippy::type_complexity)]
+impl<B, T, E, F, SF, RF, NF, CF, AF, OF, ST> BlockingRetry<B, T, E, F, SF, RF, NF, CF, AF, OF, ST>
 where
     B: Backoff,
     F: FnMut() -> Result<T, E>,
     SF: MaybeBlockingSleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
+    AF: FnMut(&E, Option<Duration>) -> Option<Duration>,
+    OF: FnMut(&T) -> bool,
 {
     /// Set the sleeper for retrying.
     ///
@@ -126,13 +158,24 @@ where
     ///     Ok(())
     /// }
     /// ```
-    pub fn sleep<SN: BlockingSleeper>(self, sleep_fn: SN) -> BlockingRetry<B, T, E, F, SN, RF, NF> {
+    pub fn sleep<SN: BlockingSleeper>(
+        self,
+        sleep_fn: SN,
+    ) -> BlockingRetry<B, T, E, F, SN, RF, NF, CF, AF, OF, ST> {
         BlockingRetry {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
             f: self.f,
             sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
         }
     }
 
@@ -164,13 +207,21 @@ where
     pub fn when<RN: FnMut(&E) -> bool>(
         self,
         retryable: RN,
-    ) -> BlockingRetry<B, T, E, F, SF, RN, NF> {
+    ) -> BlockingRetry<B, T, E, F, SF, RN, NF, CF, AF, OF, ST> {
         BlockingRetry {
             backoff: self.backoff,
             retryable,
             notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
         }
     }
 
@@ -208,43 +259,443 @@ where
     pub fn notify<NN: FnMut(&E, Duration)>(
         self,
         notify: NN,
-    ) -> BlockingRetry<B, T, E, F, SF, RF, NN> {
+    ) -> BlockingRetry<B, T, E, F, SF, RF, NN, CF, AF, OF, ST> {
         BlockingRetry {
             backoff: self.backoff,
             retryable: self.retryable,
             notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
+        }
+    }
+
+    /// Set the conditions for retrying with the state of the retry.
+    ///
+    /// This is the same as `when`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    /// `RetryState::next_delay` is always `None` here, since the backoff hasn't been consulted yet.
+    ///
+    /// If not specified, all errors are considered retryable.
+    pub fn when_with_state<RN: FnMut(&E, &RetryState) -> bool>(
+        self,
+        retryable: RN,
+    ) -> BlockingRetry<B, T, E, F, SF, WithState<RN>, NF, CF, AF, OF, ST> {
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: WithState(retryable),
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the state of the retry.
+    ///
+    /// This is the same as `notify`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn notify_with_state<NN: FnMut(&E, Duration, &RetryState)>(
+        self,
+        notify: NN,
+    ) -> BlockingRetry<B, T, E, F, SF, RF, WithState<NN>, CF, AF, OF, ST> {
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: WithState(notify),
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
+        }
+    }
+
+    /// Set to adjust the delay before the next retry.
+    ///
+    /// The input function will be invoked with the error and the delay planned by the backoff,
+    /// which is `None` if the backoff has been exhausted. It returns the delay that will be used instead:
+    ///
+    /// - `Some(Duration)` indicates to sleep for the returned duration and retry, even if the backoff has been exhausted.
+    /// - `None` indicates to stop retrying and return the current error.
+    ///
+    /// The adjusted delay is what `notify` receives and what the sleeper sleeps for.
+    ///
+    /// If not specified, the delay planned by the backoff is used.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::BlockingRetryable;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// #[derive(Debug)]
+    /// struct RetryAfterError(Option<Duration>);
+    ///
+    /// fn fetch() -> Result<String, RetryAfterError> {
+    ///     Err(RetryAfterError(Some(Duration::from_secs(3))))
+    /// }
+    ///
+    /// fn main() -> Result<()> {
+    ///     let retry = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         // Prefer the delay provided by the error.
+    ///         .adjust(|err, dur| err.0.or(dur));
+    ///     let content = retry.call();
+    ///     println!("fetch result: {:?}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn adjust<AN: FnMut(&E, Option<Duration>) -> Option<Duration>>(
+        self,
+        adjust: AN,
+    ) -> BlockingRetry<B, T, E, F, SF, RF, NF, CF, AN, OF, ST> {
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust,
+            when_ok: self.when_ok,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
+        }
+    }
+
+    /// Set the conditions for retrying on successful results.
+    ///
+    /// Sometimes a function returns `Ok` with a value that means "try again", like a pending status
+    /// or an empty list. If the input function returns `true` for a value, the function will be
+    /// retried under the same backoff. Once the backoff has been exhausted, the last value will be
+    /// returned as `Ok`.
+    ///
+    /// `notify` and `adjust` are not invoked for retried values, since there is no error.
+    ///
+    /// If not specified, all values are returned directly.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::BlockingRetryable;
+    /// use backon::ConstantBuilder;
+    ///
+    /// fn poll_jobs() -> Result<Vec<String>> {
+    ///     Ok(vec![])
+    /// }
+    ///
+    /// fn main() -> Result<()> {
+    ///     let jobs = poll_jobs
+    ///         .retry(ConstantBuilder::default())
+    ///         // Poll again if there are no jobs yet.
+    ///         .when_ok(|jobs| jobs.is_empty())
+    ///         .call()?;
+    ///     println!("got jobs: {:?}", jobs);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn when_ok<ON: FnMut(&T) -> bool>(
+        self,
+        when_ok: ON,
+    ) -> BlockingRetry<B, T, E, F, SF, RF, NF, CF, AF, ON, ST> {
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
         }
     }
+
+    /// Record the error of every failed attempt in the tracing events, using its [`Display`] output.
+    ///
+    /// By default, the events of failed attempts only carry the attempt and the delay, so that errors
+    /// don't need to implement [`Display`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::BlockingRetryable;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// fn fetch() -> Result<String> {
+    ///     Ok("hello, world!".to_string())
+    /// }
+    ///
+    /// fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .trace_errors()
+    ///         .call()?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn trace_errors(mut self) -> Self
+    where
+        E: Display,
+    {
+        self.trace_error = Some(crate::trace::display_error);
+        self
+    }
+
+    /// Return the [`RetryStats`][crate::RetryStats] alongside the result.
+    ///
+    /// After calling this, the retry returns `(result, stats)` instead of just the result, which makes it easy
+    /// to report how many attempts and how much time a call took without counting inside `notify`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::BlockingRetryable;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// fn fetch() -> Result<String> {
+    ///     Ok("hello, world!".to_string())
+    /// }
+    ///
+    /// fn main() -> Result<()> {
+    ///     let (content, stats) = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .with_stats()
+    ///         .call();
+    ///     println!(
+    ///         "fetch finished after {} attempts in {:?}",
+    ///         stats.attempts, stats.elapsed
+    ///     );
+    ///     println!("fetch succeeded: {}", content?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_stats(self) -> BlockingRetry<B, T, E, F, SF, RF, NF, CF, AF, OF, CollectStats> {
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: CollectStats,
+        }
+    }
+
+    /// Set the clock for retrying.
+    ///
+    /// The clock should implement the [`Clock`] trait. The simplest way is to use a closure like `Fn() -> Duration`.
+    ///
+    /// If not specified, we use the [`DefaultClock`]. The clock is only used to honor
+    /// [`BlockingRetry::timeout`] and [`BlockingRetry::deadline`].
+    pub fn clock<CN: Clock>(
+        self,
+        clock: CN,
+    ) -> BlockingRetry<B, T, E, F, SF, RF, NF, CN, AF, OF, ST> {
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            stats: self.stats,
+        }
+    }
+
+    /// Set the timeout for the whole retry.
+    ///
+    /// The timeout starts when the retry is called. If the next sleep would end after the timeout
+    /// elapsed, the last error will be returned directly instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::BlockingRetryable;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// fn fetch() -> Result<String> {
+    ///     Ok("hello, world!".to_string())
+    /// }
+    ///
+    /// fn main() -> Result<()> {
+    ///     let retry = fetch
+    ///         .retry(ExponentialBuilder::default().without_max_times())
+    ///         .timeout(Duration::from_secs(30));
+    ///     let content = retry.call()?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self
+    where
+        CF: Clock,
+    {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the deadline for the whole retry.
+    ///
+    /// If the next sleep would end after the deadline, the last error will be returned directly
+    /// instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    /// If a timeout is set too, the retry stops at whichever comes first.
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn deadline(mut self, deadline: std::time::Instant) -> Self
+    where
+        CF: Clock,
+    {
+        self.deadline = Some(crate::clock::instant_to_deadline(deadline));
+        self
+    }
 }
 
-impl<B, T, E, F, SF, RF, NF> BlockingRetry<B, T, E, F, SF, RF, NF>
+impl<B, T, E, F, SF, RF, NF, CF, AF, OF, ST> BlockingRetry<B, T, E, F, SF, RF, NF, CF, AF, OF, ST>
 where
     B: Backoff,
     F: FnMut() -> Result<T, E>,
     SF: BlockingSleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
+    AF: FnMut(&E, Option<Duration>) -> Option<Duration>,
+    OF: FnMut(&T) -> bool,
+    ST: StatsOutput<Result<T, E>>,
 {
     /// Call the retried function.
     ///
     /// TODO: implement [`FnOnce`] after it stable.
-    pub fn call(mut self) -> Result<T, E> {
+    pub fn call(mut self) -> ST::Output {
+        #[cfg(feature = "tracing")]
+        let span = self.progress.span();
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let res = self.call_result();
+        let stats = self.progress.stats(&self.clock);
+        #[cfg(feature = "tracing")]
+        crate::trace::record_outcome(&res, &stats);
+        ST::output(res, stats)
+    }
+
+    /// Call the retried function until it returns the result of the last attempt.
+    fn call_result(&mut self) -> Result<T, E> {
+        // The timeout starts with the first attempt, where the deadline is moved onto the clock as well.
+        let deadline = resolve_deadline(&self.clock, self.timeout, self.deadline);
+
         loop {
+            self.progress.start_attempt(&self.clock);
             let result = (self.f)();
 
             match result {
-                Ok(v) => return Ok(v),
+                Ok(v) => {
+                    if !(self.when_ok)(&v) {
+                        return Ok(v);
+                    }
+
+                    match self.backoff.next() {
+                        None => return Ok(v),
+                        Some(dur) => {
+                            // If the next attempt would start after the deadline, return value directly.
+                            if let (Some(deadline), Some(now)) = (deadline, self.clock.try_now()) {
+                                if now.saturating_add(dur) > deadline {
+                                    return Ok(v);
+                                }
+                            }
+
+                            self.progress.sleep(dur);
+                            self.sleep_fn.sleep(dur);
+                        }
+                    }
+                }
                 Err(err) => {
-                    if !(self.retryable)(&err) {
+                    if !self
+                        .retryable
+                        .should_retry(&err, &self.progress.state(&self.clock, None))
+                    {
                         return Err(err);
                     }
 
-                    match self.backoff.next() {
+                    match (self.adjust)(&err, self.backoff.next()) {
                         None => return Err(err),
                         Some(dur) => {
-                            (self.notify)(&err, dur);
+                            // If the next attempt would start after the deadline, return error directly.
+                            if let (Some(deadline), Some(now)) = (deadline, self.clock.try_now()) {
+                                if now.saturating_add(dur) > deadline {
+                                    return Err(err);
+                                }
+                            }
+
+                            let state = self.progress.state(&self.clock, Some(dur));
+                            #[cfg(feature = "tracing")]
+                            crate::trace::record_retry(
+                                state.attempt,
+                                dur,
+                                self.trace_error.map(|display| display(&err)),
+                            );
+                            self.notify.notify(&err, dur, &state);
+                            self.progress.sleep(dur);
                             self.sleep_fn.sleep(dur);
                         }
                     }
@@ -256,12 +707,15 @@ where
 #[cfg(test)]
 mod tests {
     use alloc::string::ToString;
+    use alloc::sync::Arc;
     use alloc::vec;
     use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicU64, Ordering};
     use core::time::Duration;
     use spin::Mutex;
 
     use super::*;
+    use crate::ConstantBuilder;
     use crate::ExponentialBuilder;
 
     fn always_error() -> anyhow::Result<()> {
@@ -330,6 +784,90 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_retry_when_ok() -> anyhow::Result<()> {
+        let attempts = Mutex::new(0);
+
+        let f = || {
+            let mut x = attempts.lock();
+            *x += 1;
+            Ok::<_, anyhow::Error>(*x)
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f.retry(backoff).when_ok(|v| *v < 2).call();
+
+        assert_eq!(result?, 2);
+        assert_eq!(*attempts.lock(), 2);
+
+        *attempts.lock() = 0;
+        let result = f.retry(backoff).when_ok(|_| true).call();
+
+        // The last value is returned once the backoff has been exhausted.
+        assert_eq!(result?, 4);
+        assert_eq!(*attempts.lock(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_with_stats() -> anyhow::Result<()> {
+        let error_times = Mutex::new(0);
+
+        let f = || {
+            let mut x = error_times.lock();
+            *x += 1;
+            if *x < 3 {
+                Err(anyhow::anyhow!("retryable"))
+            } else {
+                Ok(())
+            }
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let (result, stats) = f.retry(backoff).with_stats().call();
+
+        assert!(result.is_ok());
+        assert_eq!(stats.attempts, 3);
+        assert_eq!(stats.total_slept, Duration::from_millis(3));
+        assert!(stats.elapsed >= stats.total_slept);
+        assert!(!stats.first_attempt);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_with_adjust() -> anyhow::Result<()> {
+        let error_times = Mutex::new(0);
+
+        let f = || {
+            *error_times.lock() += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let mut delays = vec![];
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .adjust(|_, dur| {
+                // Keep retrying with a fixed delay after the backoff has been exhausted.
+                Some(dur.unwrap_or(Duration::from_millis(1))).filter(|_| *error_times.lock() < 5)
+            })
+            .notify(|_, dur| delays.push(dur))
+            .call();
+
+        assert!(result.is_err());
+        assert_eq!(*error_times.lock(), 5);
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(1),
+                Duration::from_millis(2),
+                Duration::from_millis(4),
+                Duration::from_millis(1),
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_fn_mut_when_and_notify() -> anyhow::Result<()> {
         let mut calls_retryable: Vec<()> = vec![];
@@ -357,4 +895,110 @@ mod tests {
         assert_eq!(calls_notify.len(), 3);
         Ok(())
     }
+
+    #[test]
+    fn test_retry_with_state() -> anyhow::Result<()> {
+        let f = || Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"));
+
+        let mut attempts = vec![];
+        let mut total_slept = vec![];
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .when_with_state(|_, state| {
+                attempts.push(state.attempt);
+                state.attempt < 3
+            })
+            .notify_with_state(|_, _, state| total_slept.push(state.total_slept))
+            .call();
+
+        assert!(result.is_err());
+        assert_eq!(attempts, vec![1, 2, 3]);
+        assert_eq!(total_slept, vec![Duration::ZERO, Duration::from_millis(1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_with_timeout() -> anyhow::Result<()> {
+        let now = Arc::new(AtomicU64::new(0));
+        let error_times = Mutex::new(0);
+
+        let f = || {
+            *error_times.lock() += 1;
+            // Every attempt takes 1s.
+            now.fetch_add(1000, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let result = f
+            .retry(ConstantBuilder::default().without_max_times())
+            .sleep(move |dur: Duration| {
+                sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+            })
+            .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+            .timeout(Duration::from_millis(3500))
+            .call();
+
+        assert!(result.is_err());
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(*error_times.lock(), 2);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_retry_with_deadline() -> anyhow::Result<()> {
+        let now = Arc::new(AtomicU64::new(0));
+        let error_times = Mutex::new(0);
+
+        let f = || {
+            *error_times.lock() += 1;
+            // Every attempt takes 1s.
+            now.fetch_add(1000, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let result = f
+            .retry(ConstantBuilder::default().without_max_times())
+            .sleep(move |dur: Duration| {
+                sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+            })
+            // The deadline can be set before the clock.
+            .deadline(std::time::Instant::now() + Duration::from_millis(3500))
+            .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+            .call();
+
+        assert!(result.is_err());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(*error_times.lock(), 2);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_retry_with_deadline_and_timeout() -> anyhow::Result<()> {
+        let error_times = Mutex::new(0);
+
+        let f = || {
+            *error_times.lock() += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let result = f
+            .retry(ConstantBuilder::default().without_max_times())
+            .sleep(|_| {})
+            .deadline(std::time::Instant::now())
+            .timeout(Duration::from_secs(60))
+            .call();
+
+        assert!(result.is_err());
+        // The deadline is earlier than the timeout, so there is no time to retry.
+        assert_eq!(*error_times.lock(), 1);
+        Ok(())
+    }
 }
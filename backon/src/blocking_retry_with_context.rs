@@ -2,7 +2,12 @@ use core::time::Duration;
 
 use crate::backoff::BackoffBuilder;
 use crate::blocking_sleep::MaybeBlockingSleeper;
-use crate::{Backoff, BlockingSleeper, DefaultBlockingSleeper};
+use crate::clock::resolve_deadline;
+use crate::clock::MaybeClock;
+use crate::retry_state::{
+    CollectStats, RetryCondition, RetryNotify, RetryProgress, StatsOutput, WithState,
+};
+use crate::{Backoff, BlockingSleeper, Clock, DefaultBlockingSleeper, DefaultClock, RetryState};
 
 /// BlockingRetryableWithContext adds retry support for blocking functions.
 pub trait BlockingRetryableWithContext<
@@ -37,12 +42,19 @@ pub struct BlockingRetryWithContext<
     SF: MaybeBlockingSleeper = DefaultBlockingSleeper,
     RF = fn(&E) -> bool,
     NF = fn(&E, Duration),
+    CF: MaybeClock = DefaultClock,
+    ST = (),
 > {
     backoff: B,
     retryable: RF,
     notify: NF,
     f: F,
     sleep_fn: SF,
+    clock: CF,
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    progress: RetryProgress,
+    stats: ST,
     ctx: Option<Ctx>,
 }
 
@@ -58,30 +70,46 @@ where
             retryable: |_: &E| true,
             notify: |_: &E, _: Duration| {},
             sleep_fn: DefaultBlockingSleeper::default(),
+            clock: DefaultClock::default(),
+            timeout: None,
+            deadline: None,
+            progress: RetryProgress::default(),
+            stats: (),
             f,
             ctx: None,
         }
     }
 }
 
-impl<B, T, E, Ctx, F, SF, RF, NF> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF>
+#[allow(clippy::type_complexity)]
+impl<B, T, E, Ctx, F, SF, RF, NF, CF, ST>
+    BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, CF, ST>
 where
     B: Backoff,
     F: FnMut(Ctx) -> (Ctx, Result<T, E>),
     SF: MaybeBlockingSleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
 {
     /// Set the context for retrying.
     ///
     /// Context is used to capture ownership manually to prevent lifetime issues.
-    pub fn context(self, context: Ctx) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF> {
+    pub fn context(
+        self,
+        context: Ctx,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, CF, ST> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
             ctx: Some(context),
         }
     }
@@ -94,13 +122,18 @@ where
     pub fn sleep<SN: BlockingSleeper>(
         self,
         sleep_fn: SN,
-    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SN, RF, NF> {
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SN, RF, NF, CF, ST> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
             f: self.f,
             sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
             ctx: self.ctx,
         }
     }
@@ -111,13 +144,18 @@ where
     pub fn when<RN: FnMut(&E) -> bool>(
         self,
         retryable: RN,
-    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RN, NF> {
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RN, NF, CF, ST> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable,
             notify: self.notify,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
             ctx: self.ctx,
         }
     }
@@ -130,32 +168,178 @@ where
     pub fn notify<NN: FnMut(&E, Duration)>(
         self,
         notify: NN,
-    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NN> {
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NN, CF, ST> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Set the conditions for retrying with the state of the retry.
+    ///
+    /// This is the same as `when`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    /// `RetryState::next_delay` is always `None` here, since the backoff hasn't been consulted yet.
+    ///
+    /// If not specified, all errors are considered retryable.
+    pub fn when_with_state<RN: FnMut(&E, &RetryState) -> bool>(
+        self,
+        retryable: RN,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, WithState<RN>, NF, CF, ST> {
+        BlockingRetryWithContext {
+            backoff: self.backoff,
+            retryable: WithState(retryable),
+            notify: self.notify,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the state of the retry.
+    ///
+    /// This is the same as `notify`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn notify_with_state<NN: FnMut(&E, Duration, &RetryState)>(
+        self,
+        notify: NN,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, WithState<NN>, CF, ST> {
+        BlockingRetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: WithState(notify),
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Return the [`RetryStats`][crate::RetryStats] alongside the result.
+    ///
+    /// After calling this, the retry returns `((ctx, result), stats)` instead of just `(ctx, result)`, which makes
+    /// it easy to report how many attempts and how much time a call took without counting inside `notify`.
+    pub fn with_stats(
+        self,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, CF, CollectStats> {
+        BlockingRetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: CollectStats,
             ctx: self.ctx,
         }
     }
+
+    /// Set the clock for retrying.
+    ///
+    /// The clock should implement the [`Clock`] trait. The simplest way is to use a closure like `Fn() -> Duration`.
+    ///
+    /// If not specified, we use the [`DefaultClock`]. The clock is only used to honor
+    /// [`BlockingRetryWithContext::timeout`] and [`BlockingRetryWithContext::deadline`].
+    pub fn clock<CN: Clock>(
+        self,
+        clock: CN,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, CN, ST> {
+        BlockingRetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Set the timeout for the whole retry.
+    ///
+    /// The timeout starts when the retry is called. If the next sleep would end after the timeout
+    /// elapsed, the last error will be returned directly instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    pub fn timeout(mut self, timeout: Duration) -> Self
+    where
+        CF: Clock,
+    {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the deadline for the whole retry.
+    ///
+    /// If the next sleep would end after the deadline, the last error will be returned directly
+    /// instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    /// If a timeout is set too, the retry stops at whichever comes first.
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn deadline(mut self, deadline: std::time::Instant) -> Self
+    where
+        CF: Clock,
+    {
+        self.deadline = Some(crate::clock::instant_to_deadline(deadline));
+        self
+    }
 }
 
-impl<B, T, E, Ctx, F, SF, RF, NF> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF>
+impl<B, T, E, Ctx, F, SF, RF, NF, CF, ST>
+    BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, CF, ST>
 where
     B: Backoff,
     F: FnMut(Ctx) -> (Ctx, Result<T, E>),
     SF: BlockingSleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
+    ST: StatsOutput<(Ctx, Result<T, E>)>,
 {
     /// Call the retried function.
     ///
     /// TODO: implement [`FnOnce`] after it stable.
-    pub fn call(mut self) -> (Ctx, Result<T, E>) {
+    pub fn call(mut self) -> ST::Output {
+        let res = self.call_result();
+        ST::output(res, self.progress.stats(&self.clock))
+    }
+
+    /// Call the retried function until it returns the result of the last attempt.
+    fn call_result(&mut self) -> (Ctx, Result<T, E>) {
         let mut ctx = self.ctx.take().expect("context must be valid");
+        // The timeout starts with the first attempt, where the deadline is moved onto the clock as well.
+        let deadline = resolve_deadline(&self.clock, self.timeout, self.deadline);
+
         loop {
+            self.progress.start_attempt(&self.clock);
             let (xctx, result) = (self.f)(ctx);
             // return ctx ownership back
             ctx = xctx;
@@ -163,14 +347,29 @@ where
             match result {
                 Ok(v) => return (ctx, Ok(v)),
                 Err(err) => {
-                    if !(self.retryable)(&err) {
+                    if !self
+                        .retryable
+                        .should_retry(&err, &self.progress.state(&self.clock, None))
+                    {
                         return (ctx, Err(err));
                     }
 
                     match self.backoff.next() {
                         None => return (ctx, Err(err)),
                         Some(dur) => {
-                            (self.notify)(&err, dur);
+                            // If the next attempt would start after the deadline, return error directly.
+                            if let (Some(deadline), Some(now)) = (deadline, self.clock.try_now()) {
+                                if now.saturating_add(dur) > deadline {
+                                    return (ctx, Err(err));
+                                }
+                            }
+
+                            self.notify.notify(
+                                &err,
+                                dur,
+                                &self.progress.state(&self.clock, Some(dur)),
+                            );
+                            self.progress.sleep(dur);
                             self.sleep_fn.sleep(dur);
                         }
                     }
@@ -183,10 +382,13 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ConstantBuilder;
     use crate::ExponentialBuilder;
     use alloc::string::ToString;
+    use alloc::sync::Arc;
     use anyhow::anyhow;
     use anyhow::Result;
+    use core::sync::atomic::{AtomicU64, Ordering};
     use core::time::Duration;
     use spin::Mutex;
 
@@ -228,4 +430,121 @@ mod tests {
         assert_eq!(*error_times.lock(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_retry_with_stats() -> Result<()> {
+        let test = Test;
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let ((_, result), stats) = {
+            |mut v: Test| {
+                let res = v.hello();
+                (v, res)
+            }
+        }
+        .retry(backoff)
+        .context(test)
+        .with_stats()
+        .call();
+
+        assert!(result.is_err());
+        assert_eq!(stats.attempts, 4);
+        assert_eq!(stats.total_slept, Duration::from_millis(7));
+        assert!(!stats.first_attempt);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_with_state() -> Result<()> {
+        let test = Test;
+
+        let mut notified = 0;
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let (_, result) = {
+            |mut v: Test| {
+                let res = v.hello();
+                (v, res)
+            }
+        }
+        .retry(backoff)
+        .context(test)
+        .when_with_state(|_, state| state.attempt < 2)
+        .notify_with_state(|_, _, state| {
+            assert_eq!(1, state.attempt);
+            notified += 1;
+        })
+        .call();
+
+        assert!(result.is_err());
+        assert_eq!(1, notified);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_with_timeout() -> Result<()> {
+        let now = Arc::new(AtomicU64::new(0));
+        let error_times = Mutex::new(0);
+
+        let test = Test;
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let (_, result) = {
+            |mut v: Test| {
+                *error_times.lock() += 1;
+                now.fetch_add(1000, Ordering::Relaxed);
+
+                let res = v.hello();
+                (v, res)
+            }
+        }
+        .retry(ConstantBuilder::default().without_max_times())
+        .context(test)
+        .sleep(move |dur: Duration| {
+            sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+        })
+        .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+        .timeout(Duration::from_millis(3500))
+        .call();
+
+        assert!(result.is_err());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(*error_times.lock(), 2);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_retry_with_deadline() -> Result<()> {
+        let now = Arc::new(AtomicU64::new(0));
+        let error_times = Mutex::new(0);
+
+        let test = Test;
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let (_, result) = {
+            |mut v: Test| {
+                *error_times.lock() += 1;
+                now.fetch_add(1000, Ordering::Relaxed);
+
+                let res = v.hello();
+                (v, res)
+            }
+        }
+        .retry(ConstantBuilder::default().without_max_times())
+        .context(test)
+        .sleep(move |dur: Duration| {
+            sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+        })
+        // The deadline can be set before the clock.
+        .deadline(std::time::Instant::now() + Duration::from_millis(3500))
+        .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+        .call();
+
+        assert!(result.is_err());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(*error_times.lock(), 2);
+        Ok(())
+    }
 }
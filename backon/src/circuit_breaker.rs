@@ -0,0 +1,416 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::fmt;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+use crate::Clock;
+
+/// CircuitBreakerBuilder is used to create a [`CircuitBreaker`].
+///
+/// # Default
+///
+/// - consecutive_failures: 5
+/// - failure_rate: None
+/// - cool_down: 30s
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::CircuitBreakerBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Share the breaker between all calls to the same service.
+///     let cb = CircuitBreakerBuilder::default()
+///         .with_consecutive_failures(3)
+///         .with_cool_down(Duration::from_secs(10))
+///         .build();
+///
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .circuit_breaker(&cb)
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerBuilder {
+    consecutive_failures: Option<usize>,
+    failure_rate: Option<(f32, usize)>,
+    cool_down: Duration,
+}
+
+impl Default for CircuitBreakerBuilder {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: Some(5),
+            failure_rate: None,
+            cool_down: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CircuitBreakerBuilder {
+    /// Set the number of consecutive failures that will open the circuit.
+    pub fn with_consecutive_failures(mut self, failures: usize) -> Self {
+        self.consecutive_failures = Some(failures);
+        self
+    }
+
+    /// Set no consecutive failures threshold.
+    ///
+    /// The circuit will not be opened by consecutive failures.
+    pub fn without_consecutive_failures(mut self) -> Self {
+        self.consecutive_failures = None;
+        self
+    }
+
+    /// Set the failure rate that will open the circuit.
+    ///
+    /// Calls are counted in windows of `window` calls. Once a window is full, the circuit will be
+    /// opened if the rate of failed calls within it is at least `rate`, which is between `0.0` and `1.0`.
+    pub fn with_failure_rate(mut self, rate: f32, window: usize) -> Self {
+        self.failure_rate = Some((rate, window.max(1)));
+        self
+    }
+
+    /// Set no failure rate threshold.
+    ///
+    /// The circuit will not be opened by failure rate.
+    pub fn without_failure_rate(mut self) -> Self {
+        self.failure_rate = None;
+        self
+    }
+
+    /// Set the cool-down period of the circuit.
+    ///
+    /// Once opened, all calls will be rejected until the cool-down has elapsed. Then the circuit becomes
+    /// half-open and lets a single trial call through: the circuit will be closed if it succeeds and opened
+    /// again if it fails.
+    ///
+    /// Sub-millisecond precision of the cool-down is truncated. On 32-bit targets, the clock of the circuit
+    /// wraps after about 49 days, so longer cool-downs are capped at that, and a circuit left open for
+    /// that long may be considered to be cooling down again.
+    pub fn with_cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+
+    /// Build a [`CircuitBreaker`] with the [`DefaultClock`][crate::DefaultClock].
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn build(self) -> CircuitBreaker {
+        self.build_with_clock(crate::StdClock)
+    }
+
+    /// Build a [`CircuitBreaker`] with the given clock.
+    pub fn build_with_clock<C: Clock + Send + Sync>(self, clock: C) -> CircuitBreaker {
+        CircuitBreaker {
+            inner: Arc::new(Inner {
+                config: self,
+                clock: Box::new(clock),
+                state: AtomicU8::new(CLOSED),
+                opened_at: AtomicUsize::new(0),
+                consecutive_failures: AtomicUsize::new(0),
+                window_calls: AtomicUsize::new(0),
+                window_failures: AtomicUsize::new(0),
+            }),
+        }
+    }
+}
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// All calls are permitted.
+    Closed,
+    /// All calls are rejected until the cool-down has elapsed.
+    Open,
+    /// A single trial call is permitted to decide whether to close the circuit.
+    HalfOpen,
+}
+
+const CLOSED: u8 = 0;
+const OPEN: u8 = 1;
+const HALF_OPEN: u8 = 2;
+
+/// CircuitBreaker stops calling a failing service for a while to give it time to recover.
+///
+/// It's cheap to clone, and all clones share the same state, so a single breaker can guard all calls
+/// to the same service. Use it with [`Retry::circuit_breaker`][crate::Retry::circuit_breaker], or call
+/// [`CircuitBreaker::try_acquire`] and record the result by hand.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: CircuitBreakerBuilder,
+    clock: Box<dyn Clock + Send + Sync>,
+
+    state: AtomicU8,
+    /// The time in milliseconds when the circuit was opened or the last trial call started.
+    ///
+    /// `AtomicU64` isn't available on every target, so the time wraps around `usize::MAX`
+    /// and is always compared with wrapping arithmetic.
+    opened_at: AtomicUsize,
+    consecutive_failures: AtomicUsize,
+    window_calls: AtomicUsize,
+    window_failures: AtomicUsize,
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("config", &self.inner.config)
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl CircuitBreaker {
+    /// Return the current state of the circuit.
+    pub fn state(&self) -> CircuitState {
+        match self.inner.state.load(Ordering::Acquire) {
+            CLOSED => CircuitState::Closed,
+            OPEN => CircuitState::Open,
+            _ => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Return `true` if a call is permitted now.
+    ///
+    /// Once the cool-down of an open circuit has elapsed, only the first caller is permitted and becomes
+    /// the trial call. If the trial call never records its result, another one is permitted after a
+    /// further cool-down.
+    pub fn try_acquire(&self) -> bool {
+        if self.inner.state.load(Ordering::Acquire) == CLOSED {
+            return true;
+        }
+
+        let now = self.now();
+        let opened_at = self.inner.opened_at.load(Ordering::Acquire);
+        if now.wrapping_sub(opened_at) < self.cool_down() {
+            return false;
+        }
+        // Only one caller wins the trial call.
+        if self
+            .inner
+            .opened_at
+            .compare_exchange(opened_at, now, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return false;
+        }
+        self.inner.state.store(HALF_OPEN, Ordering::Release);
+        true
+    }
+
+    /// Record that a call succeeded.
+    pub fn record_success(&self) {
+        match self.inner.state.load(Ordering::Acquire) {
+            CLOSED => {
+                self.inner.consecutive_failures.store(0, Ordering::Release);
+                if self.record_window(false) {
+                    self.open();
+                }
+            }
+            HALF_OPEN => self.close(),
+            // The call was permitted before the circuit opened, ignore it.
+            _ => {}
+        }
+    }
+
+    /// Record that a call failed.
+    pub fn record_failure(&self) {
+        match self.inner.state.load(Ordering::Acquire) {
+            CLOSED => {
+                let failures = self
+                    .inner
+                    .consecutive_failures
+                    .fetch_add(1, Ordering::AcqRel)
+                    + 1;
+                let too_many_failures = self
+                    .inner
+                    .config
+                    .consecutive_failures
+                    .is_some_and(|max| failures >= max);
+                if too_many_failures || self.record_window(true) {
+                    self.open();
+                }
+            }
+            HALF_OPEN => self.open(),
+            // The call was permitted before the circuit opened, ignore it.
+            _ => {}
+        }
+    }
+
+    /// Count the call in the current window, return `true` if the failure rate has been reached.
+    fn record_window(&self, failed: bool) -> bool {
+        let Some((rate, window)) = self.inner.config.failure_rate else {
+            return false;
+        };
+
+        let failures = if failed {
+            self.inner.window_failures.fetch_add(1, Ordering::AcqRel) + 1
+        } else {
+            self.inner.window_failures.load(Ordering::Acquire)
+        };
+        let calls = self.inner.window_calls.fetch_add(1, Ordering::AcqRel) + 1;
+        if calls < window {
+            return false;
+        }
+
+        self.inner.window_calls.store(0, Ordering::Release);
+        self.inner.window_failures.store(0, Ordering::Release);
+        failures as f32 >= rate * calls as f32
+    }
+
+    fn open(&self) {
+        self.inner.opened_at.store(self.now(), Ordering::Release);
+        self.inner.state.store(OPEN, Ordering::Release);
+        self.reset_counters();
+    }
+
+    fn close(&self) {
+        self.reset_counters();
+        self.inner.state.store(CLOSED, Ordering::Release);
+    }
+
+    fn reset_counters(&self) {
+        self.inner.consecutive_failures.store(0, Ordering::Release);
+        self.inner.window_calls.store(0, Ordering::Release);
+        self.inner.window_failures.store(0, Ordering::Release);
+    }
+
+    fn now(&self) -> usize {
+        // Truncating is fine since the time wraps around anyway.
+        self.inner.clock.now().as_millis() as usize
+    }
+
+    fn cool_down(&self) -> usize {
+        usize::try_from(self.inner.config.cool_down.as_millis()).unwrap_or(usize::MAX)
+    }
+}
+
+/// CircuitOpen is the error returned when a call is rejected by an open [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitOpen;
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("circuit breaker is open")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CircuitOpen {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_clock() -> (Arc<AtomicUsize>, impl Clock + Send + Sync) {
+        let now = Arc::new(AtomicUsize::new(0));
+        let clock_now = now.clone();
+        (now, move || {
+            Duration::from_millis(clock_now.load(Ordering::Relaxed) as u64)
+        })
+    }
+
+    #[test]
+    fn test_consecutive_failures() {
+        let (now, clock) = fake_clock();
+        let cb = CircuitBreakerBuilder::default()
+            .with_consecutive_failures(3)
+            .with_cool_down(Duration::from_secs(1))
+            .build_with_clock(clock);
+
+        cb.record_failure();
+        cb.record_failure();
+        cb.record_success();
+        cb.record_failure();
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(!cb.try_acquire());
+
+        // Only one trial call is permitted after the cool-down.
+        now.store(1000, Ordering::Relaxed);
+        assert!(cb.clone().try_acquire());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(!cb.try_acquire());
+
+        // The failed trial call opens the circuit again.
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+        now.store(1500, Ordering::Relaxed);
+        assert!(!cb.try_acquire());
+
+        now.store(2000, Ordering::Relaxed);
+        assert!(cb.try_acquire());
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert!(cb.try_acquire());
+    }
+
+    #[test]
+    fn test_failure_rate() {
+        let (_, clock) = fake_clock();
+        let cb = CircuitBreakerBuilder::default()
+            .without_consecutive_failures()
+            .with_failure_rate(0.5, 4)
+            .build_with_clock(clock);
+
+        // 1 of 4 failed.
+        cb.record_failure();
+        cb.record_success();
+        cb.record_success();
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        // 2 of 4 failed.
+        cb.record_failure();
+        cb.record_success();
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_abandoned_trial_call() {
+        let (now, clock) = fake_clock();
+        let cb = CircuitBreakerBuilder::default()
+            .with_consecutive_failures(1)
+            .with_cool_down(Duration::from_secs(1))
+            .build_with_clock(clock);
+
+        cb.record_failure();
+        now.store(1000, Ordering::Relaxed);
+        assert!(cb.try_acquire());
+
+        // The trial call never records its result.
+        now.store(1999, Ordering::Relaxed);
+        assert!(!cb.try_acquire());
+        now.store(2000, Ordering::Relaxed);
+        assert!(cb.try_acquire());
+    }
+}
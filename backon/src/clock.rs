@@ -0,0 +1,104 @@
+use core::time::Duration;
+
+/// A clock is used to tell the current time, so that retries can respect a deadline.
+pub trait Clock: 'static {
+    /// Return the time elapsed since an arbitrary but fixed point.
+    ///
+    /// The returned value must never decrease between calls.
+    fn now(&self) -> Duration;
+}
+
+/// A stub trait allowing non-[`Clock`] types to be used as a generic parameter in [`Retry`][crate::Retry].
+/// It does not provide actual functionality.
+#[doc(hidden)]
+pub trait MaybeClock: 'static {
+    /// Return the current time if this is a real clock.
+    fn try_now(&self) -> Option<Duration>;
+}
+
+/// All `Clock` will implement `MaybeClock`, but not vice versa.
+impl<T: Clock + ?Sized> MaybeClock for T {
+    fn try_now(&self) -> Option<Duration> {
+        Some(self.now())
+    }
+}
+
+/// All `Fn() -> Duration` implements `Clock`.
+impl<F: Fn() -> Duration + 'static> Clock for F {
+    fn now(&self) -> Duration {
+        self()
+    }
+}
+
+/// The default implementation of `Clock` when no features are enabled.
+///
+/// Setting a deadline on a retry will fail to compile without providing a valid clock via `clock`.
+#[cfg(any(not(feature = "std"), target_arch = "wasm32"))]
+pub type DefaultClock = PleaseEnableAFeatureOrProvideACustomClock;
+/// The default implementation of `Clock` while feature `std` enabled.
+///
+/// It uses [`std::time::Instant`].
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub type DefaultClock = StdClock;
+
+/// A placeholder type that does not implement [`Clock`] and will therefore fail to compile if used as one.
+///
+/// Users should enable the `std` feature of this crate on platforms that provide [`std::time::Instant`]
+/// when this type appears in compilation errors. Alternatively, a custom [`Clock`] implementation should
+/// be provided, such as in [`crate::Retry::clock`].
+#[doc(hidden)]
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PleaseEnableAFeatureOrProvideACustomClock;
+
+/// Implement `MaybeClock` but not `Clock`.
+impl MaybeClock for PleaseEnableAFeatureOrProvideACustomClock {
+    fn try_now(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// The implementation of `StdClock` uses [`std::time::Instant`].
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdClock;
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        static ORIGIN: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+
+        ORIGIN.get_or_init(std::time::Instant::now).elapsed()
+    }
+}
+
+/// Convert a [`std::time::Instant`] into the timeline of [`StdClock`].
+///
+/// Deadlines are kept on this timeline until the first attempt, so that the clock of a retry
+/// can still be changed after setting its deadline.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub(crate) fn instant_to_deadline(deadline: std::time::Instant) -> Duration {
+    StdClock
+        .now()
+        .saturating_add(deadline.saturating_duration_since(std::time::Instant::now()))
+}
+
+/// Resolve the deadline of a retry onto the timeline of its clock when the first attempt starts.
+///
+/// The timeout starts now. If both a timeout and a deadline are set, the earlier one wins.
+pub(crate) fn resolve_deadline(
+    clock: &impl MaybeClock,
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+) -> Option<Duration> {
+    let now = clock.try_now()?;
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    let deadline =
+        deadline.map(|deadline| now.saturating_add(deadline.saturating_sub(StdClock.now())));
+    let timeout = timeout.map(|timeout| now.saturating_add(timeout));
+
+    match (timeout, deadline) {
+        (Some(timeout), Some(deadline)) => Some(timeout.min(deadline)),
+        (timeout, deadline) => timeout.or(deadline),
+    }
+}
@@ -0,0 +1,536 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+use core::time::Duration;
+
+use crate::backoff::BackoffBuilder;
+use crate::clock::resolve_deadline;
+use crate::clock::MaybeClock;
+use crate::retry_state::RetryCondition;
+use crate::retry_state::RetryNotify;
+use crate::retry_state::RetryProgress;
+use crate::Backoff;
+use crate::CircuitBreaker;
+use crate::CircuitState;
+use crate::RetryBudget;
+use crate::Sleeper;
+
+/// HedgeBuilder is used to configure hedged attempts for [`Retry::hedge`][crate::Retry::hedge].
+///
+/// When an attempt hasn't finished after a delay taken from the backoff, another attempt will be started
+/// alongside it. The first successful attempt wins and the others are dropped.
+///
+/// # Default
+///
+/// - max_in_flight: 2
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::ConstantBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::HedgeBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Start another attempt every 50ms, with at most 3 attempts in flight.
+///     let hedge = HedgeBuilder::new(ConstantBuilder::default().with_delay(Duration::from_millis(50)))
+///         .with_max_in_flight(3);
+///
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .hedge(hedge)
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct HedgeBuilder<B: BackoffBuilder> {
+    backoff: B,
+    max_in_flight: usize,
+}
+
+impl<B: BackoffBuilder> HedgeBuilder<B> {
+    /// Create a new hedge builder which takes the delays before starting another attempt from the backoff.
+    ///
+    /// The backoff will be built again for every retry, so it should be cheap to clone.
+    pub fn new(backoff: B) -> Self {
+        Self {
+            backoff,
+            max_in_flight: 2,
+        }
+    }
+
+    /// Set the maximum number of attempts in flight at the same time.
+    ///
+    /// Once reached, no more attempts will be started until one of them finishes.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+}
+
+/// Future generated by [`Retry::hedge`][crate::Retry::hedge].
+///
+/// Every retry starts one attempt and keeps starting hedged attempts on the delays of the hedge backoff,
+/// until one of them succeeds. Failed attempts are dropped while others are still in flight. Once all
+/// attempts in flight have failed, the last error goes through `when` and `notify`, and the retry sleeps
+/// on its own backoff before starting over.
+pub struct Hedge<
+    B: Backoff,
+    HB: BackoffBuilder + Clone,
+    T,
+    E,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+    RF,
+    NF,
+    CF,
+> {
+    pub(crate) backoff: B,
+    pub(crate) hedge: HedgeBuilder<HB>,
+    pub(crate) retryable: RF,
+    pub(crate) notify: NF,
+    pub(crate) future_fn: FutureFn,
+    pub(crate) sleep_fn: SF,
+    pub(crate) clock: CF,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) deadline: Option<Duration>,
+    pub(crate) progress: RetryProgress,
+    pub(crate) attempt_timeout: Option<(Duration, fn() -> E)>,
+    pub(crate) circuit_breaker: Option<(CircuitBreaker, fn() -> E)>,
+    pub(crate) budget: Option<Arc<RetryBudget>>,
+
+    pub(crate) state: HedgeState<Fut, HB::Backoff, SF::Sleep>,
+}
+
+/// HedgeState maintains internal state of hedge.
+pub(crate) enum HedgeState<Fut, HedgeBackoff, SleepFut> {
+    Idle,
+    Polling {
+        attempts: Vec<HedgedAttempt<Fut, SleepFut>>,
+        hedge_backoff: HedgeBackoff,
+        hedge_timer: Option<Pin<Box<SleepFut>>>,
+    },
+    Sleeping(Pin<Box<SleepFut>>),
+}
+
+/// An attempt in flight, racing against its attempt timeout if set.
+pub(crate) struct HedgedAttempt<Fut, SleepFut> {
+    fut: Pin<Box<Fut>>,
+    timer: Option<Pin<Box<SleepFut>>>,
+}
+
+impl<Fut, SleepFut: Future<Output = ()>> HedgedAttempt<Fut, SleepFut> {
+    fn new<E>(
+        fut: Fut,
+        sleep_fn: &impl Sleeper<Sleep = SleepFut>,
+        attempt_timeout: Option<(Duration, fn() -> E)>,
+    ) -> Self {
+        Self {
+            fut: Box::pin(fut),
+            timer: attempt_timeout.map(|(timeout, _)| Box::pin(sleep_fn.sleep(timeout))),
+        }
+    }
+
+    fn poll<T, E>(
+        &mut self,
+        cx: &mut Context<'_>,
+        attempt_timeout: Option<(Duration, fn() -> E)>,
+    ) -> Poll<Result<T, E>>
+    where
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Poll::Ready(res) = self.fut.as_mut().poll(cx) {
+            return Poll::Ready(res);
+        }
+        let Some(timer) = &mut self.timer else {
+            return Poll::Pending;
+        };
+
+        ready!(timer.as_mut().poll(cx));
+        let (_, timeout_err) = attempt_timeout.expect("attempt timeout must be set");
+        Poll::Ready(Err(timeout_err()))
+    }
+}
+
+impl<B, HB, T, E, Fut, FutureFn, SF, RF, NF, CF> Future
+    for Hedge<B, HB, T, E, Fut, FutureFn, SF, RF, NF, CF>
+where
+    B: Backoff,
+    HB: BackoffBuilder + Clone,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `Hedge` struct itself,
+        // and all the futures it polls are pinned in their own boxes.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        loop {
+            match &mut this.state {
+                HedgeState::Idle => {
+                    // The timeout starts with the first attempt, where the deadline is moved onto the clock as well.
+                    if !this.progress.is_started() {
+                        this.deadline = resolve_deadline(&this.clock, this.timeout, this.deadline);
+                    }
+
+                    // Fail fast while the circuit is open.
+                    if let Some((cb, circuit_open_err)) = &this.circuit_breaker {
+                        if !cb.try_acquire() {
+                            return Poll::Ready(Err(circuit_open_err()));
+                        }
+                    }
+
+                    this.progress.start_attempt(&this.clock);
+                    let attempt = HedgedAttempt::new(
+                        (this.future_fn)(),
+                        &this.sleep_fn,
+                        this.attempt_timeout,
+                    );
+                    let mut hedge_backoff = this.hedge.backoff.clone().build();
+                    let hedge_timer = hedge_backoff
+                        .next()
+                        .map(|dur| Box::pin(this.sleep_fn.sleep(dur)));
+                    this.state = HedgeState::Polling {
+                        attempts: vec![attempt],
+                        hedge_backoff,
+                        hedge_timer,
+                    };
+                    continue;
+                }
+                HedgeState::Polling {
+                    attempts,
+                    hedge_backoff,
+                    hedge_timer,
+                } => {
+                    let mut last_err = None;
+                    let mut idx = 0;
+                    while idx < attempts.len() {
+                        let Poll::Ready(res) = attempts[idx].poll(cx, this.attempt_timeout) else {
+                            idx += 1;
+                            continue;
+                        };
+                        // Drop the finished attempt, others might still succeed if it failed.
+                        drop(attempts.swap_remove(idx));
+
+                        if let Some((cb, _)) = &this.circuit_breaker {
+                            match &res {
+                                Ok(_) => cb.record_success(),
+                                Err(_) => cb.record_failure(),
+                            }
+                        }
+                        match res {
+                            Ok(v) => {
+                                if let Some(budget) = &this.budget {
+                                    budget.deposit();
+                                }
+                                return Poll::Ready(Ok(v));
+                            }
+                            Err(err) => last_err = Some(err),
+                        }
+                    }
+
+                    if attempts.is_empty() {
+                        let err = last_err.expect("the last attempt must have failed");
+                        // If input error is not retryable, return error directly.
+                        if !this
+                            .retryable
+                            .should_retry(&err, &this.progress.state(&this.clock, None))
+                        {
+                            return Poll::Ready(Err(err));
+                        }
+                        // The next attempt would be rejected by the circuit, so don't wait for it.
+                        if let Some((cb, _)) = &this.circuit_breaker {
+                            if cb.state() != CircuitState::Closed {
+                                return Poll::Ready(Err(err));
+                            }
+                        }
+                        let Some(dur) = this.backoff.next() else {
+                            return Poll::Ready(Err(err));
+                        };
+                        // If the next attempt would start after the deadline, return error directly.
+                        if let (Some(deadline), Some(now)) = (this.deadline, this.clock.try_now()) {
+                            if now.saturating_add(dur) > deadline {
+                                return Poll::Ready(Err(err));
+                            }
+                        }
+                        // If the shared retry budget has been exhausted, return error directly.
+                        if let Some(budget) = &this.budget {
+                            if !budget.try_withdraw() {
+                                return Poll::Ready(Err(err));
+                            }
+                        }
+
+                        this.notify
+                            .notify(&err, dur, &this.progress.state(&this.clock, Some(dur)));
+                        this.progress.sleep(dur);
+                        this.state = HedgeState::Sleeping(Box::pin(this.sleep_fn.sleep(dur)));
+                        continue;
+                    }
+
+                    // Start another attempt alongside the ones in flight once the hedge delay elapsed.
+                    if attempts.len() < this.hedge.max_in_flight {
+                        if let Some(timer) = hedge_timer {
+                            ready!(timer.as_mut().poll(cx));
+
+                            // Hedged attempts add load like retries, so they must be permitted
+                            // by the circuit and paid from the budget as well. Otherwise, stop hedging.
+                            let mut permitted = true;
+                            if let Some((cb, _)) = &this.circuit_breaker {
+                                permitted = cb.try_acquire();
+                            }
+                            if let (true, Some(budget)) = (permitted, &this.budget) {
+                                permitted = budget.try_withdraw();
+                            }
+                            if !permitted {
+                                *hedge_timer = None;
+                                continue;
+                            }
+
+                            this.progress.start_attempt(&this.clock);
+                            attempts.push(HedgedAttempt::new(
+                                (this.future_fn)(),
+                                &this.sleep_fn,
+                                this.attempt_timeout,
+                            ));
+                            *hedge_timer = hedge_backoff
+                                .next()
+                                .map(|dur| Box::pin(this.sleep_fn.sleep(dur)));
+                            continue;
+                        }
+                    }
+
+                    return Poll::Pending;
+                }
+                HedgeState::Sleeping(sl) => {
+                    ready!(sl.as_mut().poll(cx));
+                    this.state = HedgeState::Idle;
+                    continue;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio-sleep"))]
+mod tests {
+    use alloc::string::ToString;
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+    use core::time::Duration;
+
+    use tokio::test;
+
+    use super::*;
+    use crate::CircuitBreakerBuilder;
+    use crate::CircuitOpen;
+    use crate::ConstantBuilder;
+    use crate::Retryable;
+
+    #[test]
+    async fn test_hedge() {
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            let call = calls.fetch_add(1, Ordering::Relaxed) + 1;
+            // The first attempt is stuck.
+            if call == 1 {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Ok::<_, anyhow::Error>(call)
+        };
+
+        let start = std::time::Instant::now();
+        let result = f
+            .retry(ConstantBuilder::default())
+            .hedge(HedgeBuilder::new(
+                ConstantBuilder::default().with_delay(Duration::from_millis(10)),
+            ))
+            .await;
+
+        assert_eq!(result.unwrap(), 2);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    async fn test_hedge_with_max_in_flight() {
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok::<_, anyhow::Error>(())
+        };
+
+        let result = f
+            .retry(ConstantBuilder::default())
+            .hedge(
+                HedgeBuilder::new(
+                    ConstantBuilder::default()
+                        .with_delay(Duration::from_millis(1))
+                        .without_max_times(),
+                )
+                .with_max_in_flight(3),
+            )
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    async fn test_hedge_with_retry() {
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let result = f
+            .retry(ConstantBuilder::default().with_delay(Duration::from_millis(1)))
+            .hedge(HedgeBuilder::new(
+                ConstantBuilder::default().with_delay(Duration::from_millis(1)),
+            ))
+            .await;
+
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        // Attempts fail immediately, so no hedged attempts are started.
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    async fn test_hedge_with_deadline_and_timeout() {
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let result = f
+            .retry(ConstantBuilder::default().with_delay(Duration::from_millis(1)))
+            .deadline(std::time::Instant::now())
+            .timeout(Duration::from_secs(60))
+            .hedge(HedgeBuilder::new(
+                ConstantBuilder::default().with_delay(Duration::from_millis(1)),
+            ))
+            .await;
+
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        // The deadline is earlier than the timeout, so there is no time to retry.
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    async fn test_hedge_with_attempt_timeout() {
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            let call = calls.fetch_add(1, Ordering::Relaxed) + 1;
+            // The first attempt is stuck.
+            if call == 1 {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Ok::<_, anyhow::Error>(call)
+        };
+
+        let start = std::time::Instant::now();
+        let result = f
+            .retry(ConstantBuilder::default().with_delay(Duration::from_millis(1)))
+            .attempt_timeout(Duration::from_millis(10), || anyhow::anyhow!("timeout"))
+            .hedge(HedgeBuilder::new(
+                ConstantBuilder::default().with_delay(Duration::from_secs(5)),
+            ))
+            .await;
+
+        // The stuck attempt timed out before hedging, so the retry made the second attempt.
+        assert_eq!(result.unwrap(), 2);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    async fn test_hedge_with_circuit_breaker() {
+        let calls = AtomicUsize::new(0);
+        let cb = CircuitBreakerBuilder::default()
+            .with_consecutive_failures(1)
+            .build();
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let hedge =
+            HedgeBuilder::new(ConstantBuilder::default().with_delay(Duration::from_millis(1)));
+        let result = f
+            .retry(ConstantBuilder::default().with_delay(Duration::from_millis(1)))
+            .circuit_breaker(&cb)
+            .hedge(hedge)
+            .await;
+        // The circuit opened after the first failure, so there is no retry.
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        let result = f
+            .retry(ConstantBuilder::default().with_delay(Duration::from_millis(1)))
+            .circuit_breaker(&cb)
+            .hedge(hedge)
+            .await;
+        assert!(result.unwrap_err().is::<CircuitOpen>());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    async fn test_hedge_with_budget() {
+        let calls = AtomicUsize::new(0);
+        let budget = Arc::new(RetryBudget::new(0, 0.0));
+
+        let f = || async {
+            let call = calls.fetch_add(1, Ordering::Relaxed) + 1;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok::<_, anyhow::Error>(call)
+        };
+
+        let result = f
+            .retry(ConstantBuilder::default())
+            .budget(&budget)
+            .hedge(
+                HedgeBuilder::new(ConstantBuilder::default().with_delay(Duration::from_millis(1)))
+                    .with_max_in_flight(3),
+            )
+            .await;
+
+        // The budget is empty, so no hedged attempts are started.
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
@@ -28,6 +28,16 @@
 //! - [`ConstantBuilder`]: backoff with a constant delay, limited to a specific number of attempts.
 //! - [`ExponentialBuilder`]: backoff with an exponential delay, also supports jitter.
 //! - [`FibonacciBuilder`]: backoff with a fibonacci delay, also supports jitter.
+//! - [`LinearBuilder`]: backoff with a delay growing by a fixed step, also supports jitter.
+//! - [`PolynomialBuilder`]: backoff with a polynomial delay, also supports jitter.
+//! - [`ScheduleBuilder`]: backoff with an explicit list of delays, which can be parsed from a string like `100ms,2s*3`.
+//! - [`DecorrelatedJitterBuilder`]: backoff with a decorrelated jitter delay, each delay is randomly picked based on the previous one.
+//!
+//! To pick a strategy at runtime, for example from a config file, convert the builder into an [`AnyBackoffBuilder`].
+//!
+//! With the `serde` feature enabled, all the builders implement `Serialize` and `Deserialize`, so retry
+//! policies can be loaded from config files. Durations are written like `1m30s` or `100ms`, missing
+//! fields take their documented defaults, and `null` disables an optional limit like `max_delay`.
 //!
 //! # Sleep
 //!
@@ -79,6 +89,13 @@
 //! BlockingSleeper` will be raised to remind you to choose or bring a real Sleeper
 //! implementation.
 //!
+//! # Clock
+//!
+//! Retry can be limited by a timeout or a deadline, which requires telling the current time.
+//! This is done by a [`Clock`]. The [`DefaultClock`] uses `std::time::Instant` when the `std`
+//! feature is enabled on non-wasm32 targets. Otherwise, a custom clock like a closure
+//! `Fn() -> Duration` should be provided via `clock` before setting the timeout.
+//!
 //! # Retry
 //!
 //! For additional examples, please visit [`docs::examples`].
@@ -150,7 +167,7 @@
 #![deny(unused_qualifications)]
 #![no_std]
 
-#[cfg(feature = "std-blocking-sleep")]
+#[cfg(feature = "std")]
 extern crate std;
 
 extern crate alloc;
@@ -158,14 +175,53 @@ extern crate alloc;
 mod backoff;
 pub use backoff::*;
 
+#[cfg(feature = "macros")]
+pub use backon_macros::retry;
+
 mod retry;
 pub use retry::Retry;
 pub use retry::Retryable;
 
+mod hedge;
+pub use hedge::Hedge;
+pub use hedge::HedgeBuilder;
+
+mod retry_budget;
+pub use retry_budget::RetryBudget;
+
+mod retry_error;
+pub use retry_error::RetryAttempt;
+pub use retry_error::RetryError;
+
+mod retry_observer;
+#[cfg(feature = "metrics")]
+pub use retry_observer::MetricsObserver;
+pub use retry_observer::RetryObserver;
+
+mod retry_state;
+pub use retry_state::RetryState;
+pub use retry_state::RetryStats;
+
 mod retry_with_context;
 pub use retry_with_context::RetryWithContext;
 pub use retry_with_context::RetryableWithContext;
 
+#[cfg(feature = "tower")]
+mod retry_layer;
+#[cfg(feature = "tower")]
+pub use retry_layer::RetryLayer;
+#[cfg(feature = "tower")]
+pub use retry_layer::RetryService;
+#[cfg(feature = "tower")]
+pub use retry_layer::RetryServiceFuture;
+
+#[cfg(feature = "stream")]
+mod stream_retry;
+#[cfg(feature = "stream")]
+pub use stream_retry::StreamRetry;
+#[cfg(feature = "stream")]
+pub use stream_retry::StreamRetryable;
+
 mod sleep;
 pub use sleep::DefaultSleeper;
 #[cfg(all(target_arch = "wasm32", feature = "gloo-timers-sleep"))]
@@ -180,11 +236,26 @@ pub use blocking_retry::{BlockingRetry, BlockingRetryable};
 mod blocking_retry_with_context;
 pub use blocking_retry_with_context::{BlockingRetryWithContext, BlockingRetryableWithContext};
 
+mod circuit_breaker;
+pub use circuit_breaker::CircuitBreaker;
+pub use circuit_breaker::CircuitBreakerBuilder;
+pub use circuit_breaker::CircuitOpen;
+pub use circuit_breaker::CircuitState;
+
+mod clock;
+pub use clock::Clock;
+pub use clock::DefaultClock;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use clock::StdClock;
+
 mod blocking_sleep;
 pub use blocking_sleep::BlockingSleeper;
 pub use blocking_sleep::DefaultBlockingSleeper;
 #[cfg(feature = "std-blocking-sleep")]
 pub use blocking_sleep::StdSleeper;
 
+#[cfg(feature = "tracing")]
+mod trace;
+
 #[cfg(docsrs)]
 pub mod docs;
@@ -1,3 +1,5 @@
+use alloc::sync::Arc;
+use core::fmt::Display;
 use core::future::Future;
 use core::pin::Pin;
 use core::task::ready;
@@ -6,9 +8,33 @@ use core::task::Poll;
 use core::time::Duration;
 
 use crate::backoff::BackoffBuilder;
+use crate::clock::resolve_deadline;
+use crate::clock::MaybeClock;
+use crate::hedge::HedgeState;
+use crate::retry_error::ErrorCollector;
+use crate::retry_error::RetryErrorSink;
+use crate::retry_observer::RetryObserver;
+use crate::retry_state::AsyncHook;
+use crate::retry_state::AsyncRetryCondition;
+use crate::retry_state::AsyncRetryNotify;
+use crate::retry_state::CollectStats;
+use crate::retry_state::RetryCondition;
+use crate::retry_state::RetryNotify;
+use crate::retry_state::RetryProgress;
+use crate::retry_state::StatsOutput;
+use crate::retry_state::WithState;
 use crate::sleep::MaybeSleeper;
 use crate::Backoff;
+use crate::CircuitBreaker;
+use crate::CircuitOpen;
+use crate::CircuitState;
+use crate::Clock;
+use crate::DefaultClock;
 use crate::DefaultSleeper;
+use crate::Hedge;
+use crate::HedgeBuilder;
+use crate::RetryBudget;
+use crate::RetryState;
 use crate::Sleeper;
 
 /// Retryable will add retry support for functions that produce futures with results.
@@ -69,16 +95,37 @@ pub struct Retry<
     Fut: Future<Output = Result<T, E>>,
     FutureFn: FnMut() -> Fut,
     SF: MaybeSleeper = DefaultSleeper,
-    RF = fn(&E) -> bool,
-    NF = fn(&E, Duration),
+    RF: AsyncRetryCondition<E> = fn(&E) -> bool,
+    NF: AsyncRetryNotify<E> = fn(&E, Duration),
+    CF: MaybeClock = DefaultClock,
+    AF = fn(&E, Option<Duration>) -> Option<Duration>,
+    OF = fn(&T) -> bool,
+    ES: RetryErrorSink<E> = (),
+    ST = (),
+    OB = (),
 > {
     backoff: B,
     retryable: RF,
     notify: NF,
+    adjust: AF,
+    when_ok: OF,
     future_fn: FutureFn,
     sleep_fn: SF,
+    clock: CF,
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    progress: RetryProgress,
+    /// Only read while the `tracing` feature is enabled.
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    trace_error: Option<fn(&E) -> &dyn Display>,
+    attempt_timeout: Option<(Duration, fn() -> E)>,
+    circuit_breaker: Option<(CircuitBreaker, fn() -> E)>,
+    budget: Option<Arc<RetryBudget>>,
+    errors: ES,
+    stats: ST,
+    observer: OB,
 
-    state: State<T, E, Fut, SF::Sleep>,
+    state: State<T, E, Fut, SF::Sleep, RF::Future, NF::Future>,
 }
 
 impl<B, T, E, Fut, FutureFn> Retry<B, T, E, Fut, FutureFn>
@@ -93,21 +140,40 @@ where
             backoff,
             retryable: |_: &E| true,
             notify: |_: &E, _: Duration| {},
+            adjust: |_: &E, dur: Option<Duration>| dur,
+            when_ok: |_: &T| false,
             future_fn,
             sleep_fn: DefaultSleeper::default(),
+            clock: DefaultClock::default(),
+            timeout: None,
+            deadline: None,
+            progress: RetryProgress::default(),
+            trace_error: None,
+            attempt_timeout: None,
+            circuit_breaker: None,
+            budget: None,
+            errors: (),
+            stats: (),
+            observer: (),
             state: State::Idle,
         }
     }
 }
 
-impl<B, T, E, Fut, FutureFn, SF, RF, NF> Retry<B, T, E, Fut, FutureFn, SF, RF, NF>
+#[allow(clippy::type_complexity)]
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, ST, OB>
+    Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, ST, OB>
 where
     B: Backoff,
     Fut: Future<Output = Result<T, E>>,
     FutureFn: FnMut() -> Fut,
     SF: MaybeSleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: AsyncRetryCondition<E>,
+    NF: AsyncRetryNotify<E>,
+    CF: MaybeClock,
+    AF: FnMut(&E, Option<Duration>) -> Option<Duration>,
+    OF: FnMut(&T) -> bool,
+    ES: RetryErrorSink<E>,
 {
     /// Set the sleeper for retrying.
     ///
@@ -139,20 +205,779 @@ where
     ///     Ok(())
     /// }
     /// ```
-    pub fn sleep<SN: Sleeper>(self, sleep_fn: SN) -> Retry<B, T, E, Fut, FutureFn, SN, RF, NF> {
+    pub fn sleep<SN: Sleeper>(
+        self,
+        sleep_fn: SN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SN, RF, NF, CF, AF, OF, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            future_fn: self.future_fn,
+            sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: State::Idle,
+        }
+    }
+
+    /// Set the conditions for retrying.
+    ///
+    /// If not specified, all errors are considered retryable.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when(|e| e.to_string() == "EOF")
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn when<RN: FnMut(&E) -> bool>(
+        self,
+        retryable: RN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RN, NF, CF, AF, OF, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: State::Idle,
+        }
+    }
+
+    /// Set to notify for all retry attempts.
+    ///
+    /// When a retry happens, the input function will be invoked with the error and the sleep duration before pausing.
+    ///
+    /// If not specified, this operation does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .notify(|err: &anyhow::Error, dur: Duration| {
+    ///             println!("retrying error {:?} with sleeping {:?}", err, dur);
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn notify<NN: FnMut(&E, Duration)>(
+        self,
+        notify: NN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NN, CF, AF, OF, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            sleep_fn: self.sleep_fn,
+            future_fn: self.future_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: State::Idle,
+        }
+    }
+
+    /// Set the conditions for retrying with the state of the retry.
+    ///
+    /// This is the same as `when`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    /// `RetryState::next_delay` is always `None` here, since the backoff hasn't been consulted yet.
+    ///
+    /// If not specified, all errors are considered retryable.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default().without_max_times())
+    ///         // Give up after 5 attempts or 10 seconds.
+    ///         .when_with_state(|_, state| {
+    ///             state.attempt < 5 && state.elapsed < Duration::from_secs(10)
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn when_with_state<RN: FnMut(&E, &RetryState) -> bool>(
+        self,
+        retryable: RN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, WithState<RN>, NF, CF, AF, OF, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable: WithState(retryable),
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: State::Idle,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the state of the retry.
+    ///
+    /// This is the same as `notify`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    ///
+    /// If not specified, this operation does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryState;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .notify_with_state(|err: &anyhow::Error, dur: Duration, state: &RetryState| {
+    ///             println!(
+    ///                 "attempt {} failed after {:?}: {:?}, retrying after {:?}",
+    ///                 state.attempt, state.elapsed, err, dur
+    ///             );
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn notify_with_state<NN: FnMut(&E, Duration, &RetryState)>(
+        self,
+        notify: NN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, WithState<NN>, CF, AF, OF, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: WithState(notify),
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            sleep_fn: self.sleep_fn,
+            future_fn: self.future_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: State::Idle,
+        }
+    }
+
+    /// Set the asynchronous conditions for retrying.
+    ///
+    /// This is the same as `when`, but the input function returns a future that resolves to whether
+    /// the error should be retried. It's useful when the decision requires async work, like refreshing
+    /// a token or looking up shared state. The returned future can't borrow the error, so clone what's needed.
+    ///
+    /// If not specified, all errors are considered retryable.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// async fn refresh_token() -> bool {
+    ///     true
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when_async(|e| {
+    ///             let unauthorized = e.to_string().contains("401");
+    ///             // Only retry if the token has been refreshed.
+    ///             async move { unauthorized && refresh_token().await }
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn when_async<RN, RFut>(
+        self,
+        retryable: RN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, AsyncHook<RN>, NF, CF, AF, OF, ES, ST, OB>
+    where
+        RN: FnMut(&E) -> RFut,
+        RFut: Future<Output = bool>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: AsyncHook(retryable),
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: State::Idle,
+        }
+    }
+
+    /// Set to notify for all retry attempts asynchronously.
+    ///
+    /// This is the same as `notify`, but the input function returns a future that will be awaited
+    /// before sleeping. It's useful when notifying requires async work, like sending to a channel.
+    /// The returned future can't borrow the error, so clone what's needed.
+    ///
+    /// If not specified, this operation does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    /// use tokio::sync::mpsc;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let (tx, mut rx) = mpsc::channel(16);
+    ///     tokio::spawn(async move {
+    ///         while let Some(msg) = rx.recv().await {
+    ///             println!("{}", msg);
+    ///         }
+    ///     });
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .notify_async(|err: &anyhow::Error, dur: Duration| {
+    ///             let tx = tx.clone();
+    ///             let msg = format!("retrying error {:?} with sleeping {:?}", err, dur);
+    ///             async move {
+    ///                 let _ = tx.send(msg).await;
+    ///             }
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn notify_async<NN, NFut>(
+        self,
+        notify: NN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, AsyncHook<NN>, CF, AF, OF, ES, ST, OB>
+    where
+        NN: FnMut(&E, Duration) -> NFut,
+        NFut: Future<Output = ()>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: AsyncHook(notify),
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: State::Idle,
+        }
+    }
+
+    /// Set to adjust the delay before the next retry.
+    ///
+    /// The input function will be invoked with the error and the delay planned by the backoff,
+    /// which is `None` if the backoff has been exhausted. It returns the delay that will be used instead:
+    ///
+    /// - `Some(Duration)` indicates to sleep for the returned duration and retry, even if the backoff has been exhausted.
+    /// - `None` indicates to stop retrying and return the current error.
+    ///
+    /// The adjusted delay is what `notify` receives and what the sleeper sleeps for.
+    ///
+    /// If not specified, the delay planned by the backoff is used.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    /// use reqwest::header::RETRY_AFTER;
+    /// use reqwest::StatusCode;
+    ///
+    /// #[derive(Debug)]
+    /// struct RetryAfterError(Option<Duration>);
+    ///
+    /// async fn fetch() -> Result<String, RetryAfterError> {
+    ///     let resp = reqwest::get("https://www.rust-lang.org")
+    ///         .await
+    ///         .map_err(|_| RetryAfterError(None))?;
+    ///     if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+    ///         let retry_after = resp
+    ///             .headers()
+    ///             .get(RETRY_AFTER)
+    ///             .and_then(|v| v.to_str().ok()?.parse().ok())
+    ///             .map(Duration::from_secs);
+    ///         return Err(RetryAfterError(retry_after));
+    ///     }
+    ///     resp.text().await.map_err(|_| RetryAfterError(None))
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         // Prefer the delay provided by the server.
+    ///         .adjust(|err, dur| err.0.or(dur))
+    ///         .await;
+    ///     println!("fetch result: {:?}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn adjust<AN: FnMut(&E, Option<Duration>) -> Option<Duration>>(
+        self,
+        adjust: AN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AN, OF, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust,
+            when_ok: self.when_ok,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: self.state,
+        }
+    }
+
+    /// Set the conditions for retrying on successful results.
+    ///
+    /// Sometimes a function returns `Ok` with a value that means "try again", like a pending status
+    /// or an empty list. If the input function returns `true` for a value, the function will be
+    /// retried under the same backoff. Once the backoff has been exhausted, the last value will be
+    /// returned as `Ok`.
+    ///
+    /// `notify` and `adjust` are not invoked for retried values, since there is no error.
+    ///
+    /// If not specified, all values are returned directly.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ConstantBuilder;
+    /// use backon::Retryable;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Status {
+    ///     Pending,
+    ///     Done(String),
+    /// }
+    ///
+    /// async fn fetch_status() -> Result<Status> {
+    ///     Ok(Status::Pending)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let status = fetch_status
+    ///         .retry(ConstantBuilder::default())
+    ///         // Poll again while the job is still pending.
+    ///         .when_ok(|status| *status == Status::Pending)
+    ///         .await?;
+    ///     println!("job finished with: {:?}", status);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn when_ok<ON: FnMut(&T) -> bool>(
+        self,
+        when_ok: ON,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, ON, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
+            state: self.state,
+        }
+    }
+
+    /// Set the circuit breaker for retrying.
+    ///
+    /// Every attempt will be recorded to the given [`CircuitBreaker`], which is shared with all its clones.
+    /// While the circuit is open, the retry will fail fast with an error converted from [`CircuitOpen`]
+    /// instead of calling the function. If an attempt fails and the circuit is no longer closed, the
+    /// error will be returned directly without consulting the backoff, since the next attempt would be
+    /// rejected anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::CircuitBreakerBuilder;
+    /// use backon::CircuitOpen;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let cb = CircuitBreakerBuilder::default().build();
+    ///
+    ///     match fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .circuit_breaker(&cb)
+    ///         .await
+    ///     {
+    ///         Ok(content) => println!("fetch succeeded: {}", content),
+    ///         Err(err) if err.is::<CircuitOpen>() => println!("service is unavailable"),
+    ///         Err(err) => return Err(err),
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn circuit_breaker(mut self, cb: &CircuitBreaker) -> Self
+    where
+        E: From<CircuitOpen>,
+    {
+        self.circuit_breaker = Some((cb.clone(), || E::from(CircuitOpen)));
+        self
+    }
+
+    /// Set the shared retry budget for retrying.
+    ///
+    /// Every successful attempt deposits into the given [`RetryBudget`], and every retry withdraws a token
+    /// from it. Once the budget has been exhausted, the current error will be returned directly instead of
+    /// retrying, no matter what the backoff says.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryBudget;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let budget = Arc::new(RetryBudget::new(10, 0.1));
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .budget(&budget)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn budget(mut self, budget: &Arc<RetryBudget>) -> Self {
+        self.budget = Some(budget.clone());
+        self
+    }
+
+    /// Record the error of every failed attempt in the tracing events, using its [`Display`] output.
+    ///
+    /// By default, the events of failed attempts only carry the attempt and the delay, so that errors
+    /// don't need to implement [`Display`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .trace_errors()
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn trace_errors(mut self) -> Self
+    where
+        E: Display,
+    {
+        self.trace_error = Some(crate::trace::display_error);
+        self
+    }
+
+    /// Collect the errors of all attempts.
+    ///
+    /// By default, only the error of the last attempt is returned once the retry gives up. After calling
+    /// this, the retry returns a [`RetryError`][crate::RetryError] instead, which holds the error of every
+    /// attempt along with when it failed and how long the retry slept after it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     match fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .collect_errors()
+    ///         .await
+    ///     {
+    ///         Ok(content) => println!("fetch succeeded: {}", content),
+    ///         Err(err) => {
+    ///             // Print the error of every attempt.
+    ///             println!("{}", err);
+    ///             return Err(err.into_last());
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn collect_errors(
+        self,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ErrorCollector<E>, ST, OB> {
         Retry {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
             future_fn: self.future_fn,
-            sleep_fn,
-            state: State::Idle,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: ErrorCollector::default(),
+            stats: self.stats,
+            observer: self.observer,
+            state: self.state,
         }
     }
 
-    /// Set the conditions for retrying.
+    /// Return the [`RetryStats`][crate::RetryStats] alongside the result.
     ///
-    /// If not specified, all errors are considered retryable.
+    /// After calling this, the retry returns `(result, stats)` instead of just the result, which makes it easy
+    /// to report how many attempts and how much time a call took without counting inside `notify`.
     ///
     /// # Examples
     ///
@@ -170,44 +995,69 @@ where
     ///
     /// #[tokio::main(flavor = "current_thread")]
     /// async fn main() -> Result<()> {
-    ///     let content = fetch
+    ///     let (content, stats) = fetch
     ///         .retry(ExponentialBuilder::default())
-    ///         .when(|e| e.to_string() == "EOF")
-    ///         .await?;
-    ///     println!("fetch succeeded: {}", content);
+    ///         .with_stats()
+    ///         .await;
+    ///     println!(
+    ///         "fetch finished after {} attempts in {:?}",
+    ///         stats.attempts, stats.elapsed
+    ///     );
+    ///     println!("fetch succeeded: {}", content?);
     ///
     ///     Ok(())
     /// }
     /// ```
-    pub fn when<RN: FnMut(&E) -> bool>(
+    pub fn with_stats(
         self,
-        retryable: RN,
-    ) -> Retry<B, T, E, Fut, FutureFn, SF, RN, NF> {
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, CollectStats, OB> {
         Retry {
             backoff: self.backoff,
-            retryable,
+            retryable: self.retryable,
             notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: CollectStats,
+            observer: self.observer,
             state: self.state,
         }
     }
 
-    /// Set to notify for all retry attempts.
+    /// Set the observer for retrying.
     ///
-    /// When a retry happens, the input function will be invoked with the error and the sleep duration before pausing.
+    /// The observer will be told about every attempt, every retry and the final outcome, see [`RetryObserver`].
     ///
     /// If not specified, this operation does nothing.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// use core::time::Duration;
+    /// use std::sync::Arc;
     ///
     /// use anyhow::Result;
     /// use backon::ExponentialBuilder;
+    /// use backon::RetryObserver;
     /// use backon::Retryable;
     ///
+    /// struct Logger;
+    ///
+    /// impl RetryObserver<anyhow::Error> for Logger {
+    ///     fn on_give_up(&self, err: &anyhow::Error, attempts: usize) {
+    ///         println!("fetch gave up after {} attempts: {}", attempts, err);
+    ///     }
+    /// }
+    ///
     /// async fn fetch() -> Result<String> {
     ///     Ok(reqwest::get("https://www.rust-lang.org")
     ///         .await?
@@ -217,53 +1067,324 @@ where
     ///
     /// #[tokio::main(flavor = "current_thread")]
     /// async fn main() -> Result<()> {
+    ///     let observer = Arc::new(Logger);
+    ///
     ///     let content = fetch
     ///         .retry(ExponentialBuilder::default())
-    ///         .notify(|err: &anyhow::Error, dur: Duration| {
-    ///             println!("retrying error {:?} with sleeping {:?}", err, dur);
-    ///         })
+    ///         .observe(observer.clone())
     ///         .await?;
     ///     println!("fetch succeeded: {}", content);
     ///
     ///     Ok(())
     /// }
     /// ```
-    pub fn notify<NN: FnMut(&E, Duration)>(
+    pub fn observe<ON: RetryObserver<E>>(
         self,
-        notify: NN,
-    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NN> {
+        observer: ON,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, ST, ON> {
         Retry {
             backoff: self.backoff,
             retryable: self.retryable,
-            notify,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
+            future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer,
+            state: self.state,
+        }
+    }
+
+    /// Set the clock for retrying.
+    ///
+    /// The clock should implement the [`Clock`] trait. The simplest way is to use a closure like `Fn() -> Duration`.
+    ///
+    /// If not specified, we use the [`DefaultClock`]. The clock is only used to honor [`Retry::timeout`] and [`Retry::deadline`].
+    pub fn clock<CN: Clock>(
+        self,
+        clock: CN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CN, AF, OF, ES, ST, OB> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            adjust: self.adjust,
+            when_ok: self.when_ok,
             future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            trace_error: self.trace_error,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            errors: self.errors,
+            stats: self.stats,
+            observer: self.observer,
             state: self.state,
         }
     }
+
+    /// Set the timeout for the whole retry.
+    ///
+    /// The timeout starts when the retry is polled for the first time. If the next sleep would end
+    /// after the timeout elapsed, the last error will be returned directly instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default().without_max_times())
+    ///         .timeout(Duration::from_secs(30))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self
+    where
+        CF: Clock,
+    {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the deadline for the whole retry.
+    ///
+    /// If the next sleep would end after the deadline, the last error will be returned directly
+    /// instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    /// If a timeout is set too, the retry stops at whichever comes first.
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn deadline(mut self, deadline: std::time::Instant) -> Self
+    where
+        CF: Clock,
+    {
+        self.deadline = Some(crate::clock::instant_to_deadline(deadline));
+        self
+    }
+
+    /// Set the timeout for every attempt.
+    ///
+    /// Each attempt will race against a sleep of the given duration from the configured sleeper.
+    /// If the sleep wins, the attempt will be dropped and the error built by `timeout_err` will be
+    /// handled like any other error, which means it goes through `when` and `notify` as well.
+    ///
+    /// If not specified, attempts will never time out.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .attempt_timeout(Duration::from_secs(5), || anyhow::anyhow!("attempt timed out"))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn attempt_timeout(mut self, timeout: Duration, timeout_err: fn() -> E) -> Self {
+        self.attempt_timeout = Some((timeout, timeout_err));
+        self
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, CF> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+    RF: RetryCondition<E> + AsyncRetryCondition<E>,
+    NF: RetryNotify<E> + AsyncRetryNotify<E>,
+    CF: MaybeClock,
+{
+    /// Start hedged attempts for every retry.
+    ///
+    /// When an attempt hasn't finished after a delay taken from the backoff of the [`HedgeBuilder`],
+    /// another attempt will be started alongside it, up to the max in flight. The first successful
+    /// attempt wins and the others are dropped. Once all attempts in flight have failed, the last error
+    /// will be handled like any other error, which means it goes through `when`, `notify` and the backoff.
+    ///
+    /// The sleeper, backoff, `when`, `notify`, clock, timeout, attempt timeout, circuit breaker and budget
+    /// of this retry are used by the returned [`Hedge`]. Only synchronous `when` and `notify` functions are
+    /// supported. Every hedged attempt has its own attempt timeout, and has to be permitted by the circuit
+    /// breaker and withdraw a token from the budget like a retry, otherwise no more hedged attempts are
+    /// started.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ConstantBuilder;
+    /// use backon::ExponentialBuilder;
+    /// use backon::HedgeBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         // Start another attempt if the first one takes longer than 100ms.
+    ///         .hedge(HedgeBuilder::new(
+    ///             ConstantBuilder::default().with_delay(Duration::from_millis(100)),
+    ///         ))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn hedge<HB: BackoffBuilder + Clone>(
+        self,
+        hedge: HedgeBuilder<HB>,
+    ) -> Hedge<B, HB, T, E, Fut, FutureFn, SF, RF, NF, CF> {
+        Hedge {
+            backoff: self.backoff,
+            hedge,
+            retryable: self.retryable,
+            notify: self.notify,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            attempt_timeout: self.attempt_timeout,
+            circuit_breaker: self.circuit_breaker,
+            budget: self.budget,
+            state: HedgeState::Idle,
+        }
+    }
 }
 
 /// State maintains internal state of retry.
 #[derive(Default)]
-enum State<T, E, Fut: Future<Output = Result<T, E>>, SleepFut: Future<Output = ()>> {
+enum State<
+    T,
+    E,
+    Fut: Future<Output = Result<T, E>>,
+    SleepFut: Future<Output = ()>,
+    WhenFut: Future<Output = bool>,
+    NotifyFut: Future<Output = ()>,
+> {
     #[default]
     Idle,
-    Polling(Fut),
+    Polling((Fut, Option<SleepFut>)),
+    Checking((Option<E>, Duration, WhenFut)),
+    Notifying((Duration, NotifyFut)),
     Sleeping(SleepFut),
 }
 
-impl<B, T, E, Fut, FutureFn, SF, RF, NF> Future for Retry<B, T, E, Fut, FutureFn, SF, RF, NF>
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, ST, OB> Future
+    for Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, ST, OB>
 where
     B: Backoff,
     Fut: Future<Output = Result<T, E>>,
     FutureFn: FnMut() -> Fut,
     SF: Sleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: AsyncRetryCondition<E>,
+    NF: AsyncRetryNotify<E>,
+    CF: MaybeClock,
+    AF: FnMut(&E, Option<Duration>) -> Option<Duration>,
+    OF: FnMut(&T) -> bool,
+    ES: RetryErrorSink<E>,
+    ST: StatsOutput<Result<T, ES::Error>>,
+    OB: RetryObserver<E>,
 {
-    type Output = Result<T, E>;
+    type Output = ST::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because the span is never pinned.
+        #[cfg(feature = "tracing")]
+        let span = unsafe { self.as_mut().get_unchecked_mut() }.progress.span();
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = ready!(self.as_mut().poll_result(cx));
+        let stats = self.progress.stats(&self.clock);
+        if res.is_ok() {
+            self.observer.on_success(stats.attempts);
+        }
+        #[cfg(feature = "tracing")]
+        crate::trace::record_outcome(&res, &stats);
+        Poll::Ready(ST::output(res, stats))
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, ST, OB>
+    Retry<B, T, E, Fut, FutureFn, SF, RF, NF, CF, AF, OF, ES, ST, OB>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+    RF: AsyncRetryCondition<E>,
+    NF: AsyncRetryNotify<E>,
+    CF: MaybeClock,
+    AF: FnMut(&E, Option<Duration>) -> Option<Duration>,
+    OF: FnMut(&T) -> bool,
+    ES: RetryErrorSink<E>,
+    OB: RetryObserver<E>,
+{
+    /// Poll the retry until it returns the result of the last attempt.
+    fn poll_result(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T, ES::Error>> {
         // Safety: This is safe because we don't move the `Retry` struct itself,
         // only its internal state.
         //
@@ -273,35 +1394,182 @@ where
         loop {
             match &mut this.state {
                 State::Idle => {
+                    // The timeout starts with the first attempt, where the deadline is moved onto the clock as well.
+                    if !this.progress.is_started() {
+                        this.deadline = resolve_deadline(&this.clock, this.timeout, this.deadline);
+                    }
+
+                    // Fail fast while the circuit is open.
+                    if let Some((cb, circuit_open_err)) = &this.circuit_breaker {
+                        if !cb.try_acquire() {
+                            let elapsed = this.progress.state(&this.clock, None).elapsed;
+                            return Poll::Ready(Err(this.give_up(circuit_open_err(), elapsed)));
+                        }
+                    }
+
+                    this.progress.start_attempt(&this.clock);
+                    this.observer
+                        .on_attempt(this.progress.state(&this.clock, None).attempt);
                     let fut = (this.future_fn)();
-                    this.state = State::Polling(fut);
+                    let timer = this
+                        .attempt_timeout
+                        .map(|(timeout, _)| this.sleep_fn.sleep(timeout));
+                    this.state = State::Polling((fut, timer));
                     continue;
                 }
-                State::Polling(fut) => {
+                State::Polling((fut, timer)) => {
                     // Safety: This is safe because we don't move the `Retry` struct and this fut,
                     // only its internal state.
                     //
                     // We do the exactly same thing like `pin_project` but without depending on it directly.
                     let mut fut = unsafe { Pin::new_unchecked(fut) };
 
-                    match ready!(fut.as_mut().poll(cx)) {
-                        Ok(v) => return Poll::Ready(Ok(v)),
-                        Err(err) => {
-                            // If input error is not retryable, return error directly.
-                            if !(this.retryable)(&err) {
-                                return Poll::Ready(Err(err));
+                    let res = match fut.as_mut().poll(cx) {
+                        Poll::Ready(res) => res,
+                        Poll::Pending => {
+                            let Some(timer) = timer else {
+                                return Poll::Pending;
+                            };
+                            // Safety: This is safe because we don't move the `Retry` struct and this timer,
+                            // only its internal state.
+                            //
+                            // We do the exactly same thing like `pin_project` but without depending on it directly.
+                            let mut timer = unsafe { Pin::new_unchecked(timer) };
+
+                            ready!(timer.as_mut().poll(cx));
+                            // The attempt timed out, it will be dropped as the state changes.
+                            let (_, timeout_err) =
+                                this.attempt_timeout.expect("attempt timeout must be set");
+                            Err(timeout_err())
+                        }
+                    };
+
+                    // A value rejected by `when_ok` counts as a failed attempt.
+                    let accepted = match &res {
+                        Ok(v) => !(this.when_ok)(v),
+                        Err(_) => false,
+                    };
+                    if let Some((cb, _)) = &this.circuit_breaker {
+                        if accepted {
+                            cb.record_success();
+                        } else {
+                            cb.record_failure();
+                        }
+                    }
+                    if let (Some(budget), true) = (&this.budget, accepted) {
+                        budget.deposit();
+                    }
+
+                    match res {
+                        Ok(v) => {
+                            if accepted {
+                                return Poll::Ready(Ok(v));
+                            }
+                            // The next attempt would be rejected by the circuit, so return value directly.
+                            if let Some((cb, _)) = &this.circuit_breaker {
+                                if cb.state() != CircuitState::Closed {
+                                    return Poll::Ready(Ok(v));
+                                }
                             }
+
                             match this.backoff.next() {
-                                None => return Poll::Ready(Err(err)),
+                                None => return Poll::Ready(Ok(v)),
                                 Some(dur) => {
-                                    (this.notify)(&err, dur);
+                                    // If the next attempt would start after the deadline, return value directly.
+                                    if let (Some(deadline), Some(now)) =
+                                        (this.deadline, this.clock.try_now())
+                                    {
+                                        if now.saturating_add(dur) > deadline {
+                                            return Poll::Ready(Ok(v));
+                                        }
+                                    }
+                                    // If the shared retry budget has been exhausted, return value directly.
+                                    if let Some(budget) = &this.budget {
+                                        if !budget.try_withdraw() {
+                                            return Poll::Ready(Ok(v));
+                                        }
+                                    }
+
+                                    this.progress.sleep(dur);
                                     this.state = State::Sleeping(this.sleep_fn.sleep(dur));
                                     continue;
                                 }
                             }
                         }
+                        Err(err) => {
+                            let state = this.progress.state(&this.clock, None);
+                            let fut = this.retryable.should_retry(&err, &state);
+                            this.state = State::Checking((Some(err), state.elapsed, fut));
+                            continue;
+                        }
+                    }
+                }
+                State::Checking((err, elapsed, fut)) => {
+                    let elapsed = *elapsed;
+                    // Safety: This is safe because we don't move the `Retry` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut fut = unsafe { Pin::new_unchecked(fut) };
+
+                    let retryable = ready!(fut.as_mut().poll(cx));
+                    let err = err.take().expect("error must be present while checking");
+                    // If input error is not retryable, return error directly.
+                    if !retryable {
+                        return Poll::Ready(Err(this.give_up(err, elapsed)));
+                    }
+                    // The next attempt would be rejected by the circuit, so don't wait for it.
+                    if let Some((cb, _)) = &this.circuit_breaker {
+                        if cb.state() != CircuitState::Closed {
+                            return Poll::Ready(Err(this.give_up(err, elapsed)));
+                        }
+                    }
+                    let dur = (this.adjust)(&err, this.backoff.next());
+                    match dur {
+                        None => return Poll::Ready(Err(this.give_up(err, elapsed))),
+                        Some(dur) => {
+                            // If the next attempt would start after the deadline, return error directly.
+                            if let (Some(deadline), Some(now)) =
+                                (this.deadline, this.clock.try_now())
+                            {
+                                if now.saturating_add(dur) > deadline {
+                                    return Poll::Ready(Err(this.give_up(err, elapsed)));
+                                }
+                            }
+                            // If the shared retry budget has been exhausted, return error directly.
+                            if let Some(budget) = &this.budget {
+                                if !budget.try_withdraw() {
+                                    return Poll::Ready(Err(this.give_up(err, elapsed)));
+                                }
+                            }
+
+                            let state = this.progress.state(&this.clock, Some(dur));
+                            #[cfg(feature = "tracing")]
+                            crate::trace::record_retry(
+                                state.attempt,
+                                dur,
+                                this.trace_error.map(|display| display(&err)),
+                            );
+                            let fut = this.notify.notify(&err, dur, &state);
+                            this.observer.on_retry(&err, dur);
+                            this.errors.record(err, elapsed, dur);
+                            this.progress.sleep(dur);
+                            this.state = State::Notifying((dur, fut));
+                            continue;
+                        }
                     }
                 }
+                State::Notifying((dur, fut)) => {
+                    // Safety: This is safe because we don't move the `Retry` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut fut = unsafe { Pin::new_unchecked(fut) };
+
+                    ready!(fut.as_mut().poll(cx));
+                    this.state = State::Sleeping(this.sleep_fn.sleep(*dur));
+                    continue;
+                }
                 State::Sleeping(sl) => {
                     // Safety: This is safe because we don't move the `Retry` struct and this fut,
                     // only its internal state.
@@ -316,6 +1584,13 @@ where
             }
         }
     }
+
+    /// Give up retrying and build the error to return.
+    fn give_up(&mut self, err: E, elapsed: Duration) -> ES::Error {
+        self.observer
+            .on_give_up(&err, self.progress.state(&self.clock, None).attempt);
+        self.errors.finish(err, elapsed)
+    }
 }
 
 #[cfg(test)]
@@ -398,6 +1673,160 @@ mod default_sleeper_tests {
         assert_eq!(*error_times.lock().await, 4);
     }
 
+    #[test]
+    async fn test_retry_with_attempt_timeout() {
+        let attempts = Mutex::new(0);
+
+        let f = || async {
+            let attempt = {
+                let mut x = attempts.lock().await;
+                *x += 1;
+                *x
+            };
+            // The first attempt hangs forever.
+            if attempt == 1 {
+                core::future::pending::<()>().await;
+            }
+            Ok::<usize, anyhow::Error>(attempt)
+        };
+
+        let mut timeouts = 0;
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .attempt_timeout(Duration::from_millis(10), || anyhow::anyhow!("timeout"))
+            .notify(|e, _| {
+                assert_eq!("timeout", e.to_string());
+                timeouts += 1;
+            })
+            .await;
+
+        assert_eq!(2, result.unwrap());
+        assert_eq!(1, timeouts);
+    }
+
+    #[test]
+    async fn test_retry_with_attempt_timeout_not_retryable() {
+        let f = || core::future::pending::<anyhow::Result<()>>();
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .attempt_timeout(Duration::from_millis(10), || anyhow::anyhow!("timeout"))
+            .when(|e| e.to_string() != "timeout")
+            .await;
+
+        assert_eq!("timeout", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    async fn test_retry_with_adjust() {
+        let error_times = Mutex::new(0);
+
+        let f = || async {
+            let mut x = error_times.lock().await;
+            *x += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let mut delays = vec![];
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .adjust(|_, dur| {
+                let attempts = *error_times.try_lock().unwrap();
+                // Retry twice with the delay provided by the error, then stop.
+                (attempts <= 2).then(|| dur.unwrap() * 2)
+            })
+            .notify(|_, dur| delays.push(dur))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(*error_times.lock().await, 3);
+        assert_eq!(
+            delays,
+            vec![Duration::from_millis(2), Duration::from_millis(4)]
+        );
+    }
+
+    #[test]
+    async fn test_retry_with_async_when_and_notify() {
+        let error_times = Mutex::new(0);
+
+        let f = || async {
+            let mut x = error_times.lock().await;
+            *x += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!(x.to_string()))
+        };
+
+        let notified = Mutex::new(vec![]);
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .when_async(|e| {
+                let retryable = e.to_string() != "3";
+                async move {
+                    tokio::task::yield_now().await;
+                    retryable
+                }
+            })
+            .notify_async(|e, dur| {
+                let msg = e.to_string();
+                let notified = &notified;
+                async move {
+                    tokio::task::yield_now().await;
+                    notified.lock().await.push((msg, dur));
+                }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!("3", result.unwrap_err().to_string());
+        assert_eq!(*error_times.lock().await, 3);
+        assert_eq!(
+            *notified.lock().await,
+            vec![
+                ("1".to_string(), Duration::from_millis(1)),
+                ("2".to_string(), Duration::from_millis(2)),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_when_ok() {
+        let attempts = Mutex::new(0);
+
+        let f = || async {
+            let mut x = attempts.lock().await;
+            *x += 1;
+            Ok::<_, anyhow::Error>(*x)
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f.retry(backoff).when_ok(|v| *v < 2).await;
+
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(*attempts.lock().await, 2);
+    }
+
+    #[test]
+    async fn test_retry_when_ok_exhausted() {
+        let attempts = Mutex::new(0);
+
+        let f = || async {
+            let mut x = attempts.lock().await;
+            *x += 1;
+            Ok::<_, anyhow::Error>(*x)
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f.retry(backoff).when_ok(|_| true).await;
+
+        // The last value is returned once the backoff has been exhausted.
+        assert_eq!(result.unwrap(), 4);
+        assert_eq!(*attempts.lock().await, 4);
+    }
+
     #[test]
     async fn test_fn_mut_when_and_notify() {
         let mut calls_retryable: Vec<()> = vec![];
@@ -428,8 +1857,15 @@ mod default_sleeper_tests {
 
 #[cfg(test)]
 mod custom_sleeper_tests {
+    use alloc::format;
+    use alloc::string::String;
     use alloc::string::ToString;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
     use core::{future::ready, time::Duration};
+    use spin::Mutex;
 
     #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test as test;
@@ -438,6 +1874,7 @@ mod custom_sleeper_tests {
     use tokio::test;
 
     use super::*;
+    use crate::ConstantBuilder;
     use crate::ExponentialBuilder;
 
     async fn always_error() -> anyhow::Result<()> {
@@ -454,4 +1891,437 @@ mod custom_sleeper_tests {
         assert!(result.is_err());
         assert_eq!("test_query meets error", result.unwrap_err().to_string());
     }
+
+    #[test]
+    async fn test_retry_with_state() {
+        let now = Arc::new(AtomicU64::new(0));
+
+        let f = || async {
+            now.fetch_add(100, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let mut when_states = vec![];
+        let mut notify_states = vec![];
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let result = f
+            .retry(ConstantBuilder::default().with_max_times(2))
+            .sleep(move |dur: Duration| {
+                sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+                ready(())
+            })
+            .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+            .when_with_state(|_, state| {
+                when_states.push(*state);
+                true
+            })
+            .notify_with_state(|_, dur, state| {
+                assert_eq!(Some(dur), state.next_delay);
+                notify_states.push(*state);
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            when_states
+                .iter()
+                .map(|s| (s.attempt, s.elapsed, s.total_slept, s.next_delay))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, Duration::from_millis(100), Duration::ZERO, None),
+                (2, Duration::from_millis(1200), Duration::from_secs(1), None),
+                (3, Duration::from_millis(2300), Duration::from_secs(2), None),
+            ]
+        );
+        assert_eq!(
+            notify_states
+                .iter()
+                .map(|s| (s.attempt, s.elapsed, s.total_slept))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, Duration::from_millis(100), Duration::ZERO),
+                (2, Duration::from_millis(1200), Duration::from_secs(1)),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_with_timeout() {
+        let now = Arc::new(AtomicU64::new(0));
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            // Every attempt takes 1s.
+            now.fetch_add(1000, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let result = f
+            .retry(ConstantBuilder::default().without_max_times())
+            .sleep(move |dur: Duration| {
+                sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+                ready(())
+            })
+            .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+            .timeout(Duration::from_millis(3500))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    async fn test_retry_with_circuit_breaker() {
+        let now = Arc::new(AtomicU64::new(0));
+        let calls = AtomicUsize::new(0);
+        let fail = core::sync::atomic::AtomicBool::new(true);
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            if fail.load(Ordering::Relaxed) {
+                Err(anyhow::anyhow!("retryable"))
+            } else {
+                Ok(())
+            }
+        };
+
+        let clock_now = now.clone();
+        let cb = crate::CircuitBreakerBuilder::default()
+            .with_consecutive_failures(2)
+            .with_cool_down(Duration::from_secs(10))
+            .build_with_clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)));
+        let backoff = ConstantBuilder::default().with_max_times(5);
+
+        // The circuit opens after the second attempt, and the backoff is skipped.
+        let result = f
+            .retry(backoff)
+            .sleep(|_| ready(()))
+            .circuit_breaker(&cb)
+            .await;
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        // Fail fast while the circuit is open.
+        let result = f
+            .retry(backoff)
+            .sleep(|_| ready(()))
+            .circuit_breaker(&cb)
+            .await;
+        assert!(result.unwrap_err().is::<CircuitOpen>());
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // The trial call after the cool-down closes the circuit.
+        now.store(10_000, Ordering::Relaxed);
+        fail.store(false, Ordering::Relaxed);
+        let result = f
+            .retry(backoff)
+            .sleep(|_| ready(()))
+            .circuit_breaker(&cb)
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    async fn test_retry_with_budget() {
+        let calls = AtomicUsize::new(0);
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let budget = Arc::new(RetryBudget::new(4, 0.5));
+        let backoff = ConstantBuilder::default().with_max_times(3);
+
+        // The first retry uses 3 tokens, the second one only has 1 token left.
+        for _ in 0..2 {
+            let result = f.retry(backoff).sleep(|_| ready(())).budget(&budget).await;
+            assert!(result.is_err());
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 4 + 2);
+        assert_eq!(budget.available(), 0);
+
+        // Successful calls deposit tokens.
+        for _ in 0..2 {
+            let result = (|| async { Ok::<_, anyhow::Error>(()) })
+                .retry(backoff)
+                .sleep(|_| ready(()))
+                .budget(&budget)
+                .await;
+            assert!(result.is_ok());
+        }
+        assert_eq!(budget.available(), 1);
+    }
+
+    #[test]
+    async fn test_retry_when_ok_with_circuit_breaker_and_budget() {
+        let calls = AtomicUsize::new(0);
+        let f = || async { Ok::<_, anyhow::Error>(calls.fetch_add(1, Ordering::Relaxed) + 1) };
+        let backoff = ConstantBuilder::default().with_max_times(5);
+
+        // Rejected values withdraw from the budget and don't refill it.
+        let budget = Arc::new(RetryBudget::new(2, 1.0));
+        let result = f
+            .retry(backoff)
+            .sleep(|_| ready(()))
+            .when_ok(|_| true)
+            .budget(&budget)
+            .await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(budget.available(), 0);
+
+        // Rejected values count as failures, and an open circuit stops the retries.
+        calls.store(0, Ordering::Relaxed);
+        let cb = crate::CircuitBreakerBuilder::default()
+            .with_consecutive_failures(2)
+            .build_with_clock(|| Duration::ZERO);
+        let result = f
+            .retry(backoff)
+            .sleep(|_| ready(()))
+            .when_ok(|_| true)
+            .circuit_breaker(&cb)
+            .await;
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    /// An observer recording every call.
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl RetryObserver<anyhow::Error> for Recorder {
+        fn on_attempt(&self, attempt: usize) {
+            self.0.lock().push(format!("attempt {attempt}"));
+        }
+
+        fn on_retry(&self, err: &anyhow::Error, dur: Duration) {
+            self.0.lock().push(format!("retry {err} after {dur:?}"));
+        }
+
+        fn on_success(&self, attempts: usize) {
+            self.0.lock().push(format!("success after {attempts}"));
+        }
+
+        fn on_give_up(&self, err: &anyhow::Error, attempts: usize) {
+            self.0
+                .lock()
+                .push(format!("give up {err} after {attempts}"));
+        }
+    }
+
+    #[test]
+    async fn test_retry_observe() {
+        let calls = AtomicUsize::new(0);
+        let f = || async {
+            let call = calls.fetch_add(1, Ordering::Relaxed) + 1;
+            match call {
+                1 => Err(anyhow::anyhow!("error {call}")),
+                _ => Ok(call),
+            }
+        };
+
+        let observer = Recorder::default();
+        let result = f
+            .retry(ConstantBuilder::default())
+            .sleep(|_| ready(()))
+            .observe(&observer)
+            .await;
+
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(
+            *observer.0.lock(),
+            vec![
+                "attempt 1",
+                "retry error 1 after 1s",
+                "attempt 2",
+                "success after 2"
+            ]
+        );
+
+        let observer = Arc::new(Recorder::default());
+        let result = always_error
+            .retry(ConstantBuilder::default().with_max_times(1))
+            .sleep(|_| ready(()))
+            .observe(observer.clone())
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            *observer.0.lock(),
+            vec![
+                "attempt 1",
+                "retry test_query meets error after 1s",
+                "attempt 2",
+                "give up test_query meets error after 2"
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_collect_errors() {
+        let now = Arc::new(AtomicU64::new(0));
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            let call = calls.fetch_add(1, Ordering::Relaxed) + 1;
+            // Every attempt takes 100ms.
+            now.fetch_add(100, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("error {call}"))
+        };
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let result = f
+            .retry(ConstantBuilder::default().with_max_times(2))
+            .sleep(move |dur: Duration| {
+                sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+                ready(())
+            })
+            .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+            .collect_errors()
+            .await;
+
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.attempts()
+                .iter()
+                .map(|a| (a.error.to_string(), a.elapsed, a.delay))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "error 1".to_string(),
+                    Duration::from_millis(100),
+                    Some(Duration::from_secs(1))
+                ),
+                (
+                    "error 2".to_string(),
+                    Duration::from_millis(1200),
+                    Some(Duration::from_secs(1))
+                ),
+                ("error 3".to_string(), Duration::from_millis(2300), None),
+            ]
+        );
+        assert_eq!("error 3", err.into_last().to_string());
+    }
+
+    #[test]
+    async fn test_retry_with_stats() {
+        let now = Arc::new(AtomicU64::new(0));
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            now.fetch_add(100, Ordering::Relaxed);
+            if calls.fetch_add(1, Ordering::Relaxed) < 2 {
+                Err(anyhow::anyhow!("retryable"))
+            } else {
+                Ok(())
+            }
+        };
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let (result, stats) = f
+            .retry(ConstantBuilder::default())
+            .sleep(move |dur: Duration| {
+                sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+                ready(())
+            })
+            .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+            .with_stats()
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(stats.attempts, 3);
+        assert_eq!(stats.total_slept, Duration::from_secs(2));
+        assert_eq!(stats.elapsed, Duration::from_millis(2300));
+        assert!(!stats.first_attempt);
+
+        let (result, stats) = (|| async { Ok::<_, anyhow::Error>(()) })
+            .retry(ConstantBuilder::default())
+            .sleep(|_| ready(()))
+            .with_stats()
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(stats.attempts, 1);
+        assert_eq!(stats.total_slept, Duration::ZERO);
+        assert!(stats.first_attempt);
+    }
+
+    #[test]
+    async fn test_retry_collect_errors_not_retryable() {
+        let (result, stats) = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .when(|_| false)
+            .collect_errors()
+            .with_stats()
+            .await;
+
+        assert_eq!(stats.attempts, 1);
+        let err = result.unwrap_err();
+        assert_eq!(err.attempts().len(), 1);
+        assert_eq!("test_query meets error", err.first().to_string());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    async fn test_retry_with_deadline() {
+        let now = Arc::new(AtomicU64::new(0));
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            // Every attempt takes 1s.
+            now.fetch_add(1000, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let result = f
+            .retry(ConstantBuilder::default().without_max_times())
+            .sleep(move |dur: Duration| {
+                sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+                ready(())
+            })
+            // The deadline can be set before the clock.
+            .deadline(std::time::Instant::now() + Duration::from_millis(3500))
+            .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+            .await;
+
+        assert!(result.is_err());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    async fn test_retry_with_deadline_and_timeout() {
+        let calls = AtomicUsize::new(0);
+
+        let f = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let result = f
+            .retry(ConstantBuilder::default().without_max_times())
+            .sleep(|_| ready(()))
+            .deadline(std::time::Instant::now())
+            .timeout(Duration::from_secs(60))
+            .await;
+
+        assert!(result.is_err());
+        // The deadline is earlier than the timeout, so there is no time to retry.
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
 }
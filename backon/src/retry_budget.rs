@@ -0,0 +1,123 @@
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// The number of milli-tokens in a token, so that fractional deposits can be stored in an atomic integer.
+const SCALE: usize = 1000;
+
+/// RetryBudget limits the number of retries shared by many retries with a token bucket.
+///
+/// Every successful call deposits a fraction of a token, and every retry withdraws a whole token.
+/// Once the budget runs out, retries stop and return the error directly until enough calls succeed again.
+/// This prevents retry storms where every caller burns its full backoff against a struggling service.
+///
+/// The budget is lock-free and is meant to be shared through [`Arc`][alloc::sync::Arc] by all retries
+/// calling the same service, see [`Retry::budget`][crate::Retry::budget].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryBudget;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Allow at most 10 retries in a burst, and one more retry for every 10 successful calls.
+///     let budget = Arc::new(RetryBudget::new(10, 0.1));
+///
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .budget(&budget)
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RetryBudget {
+    max_tokens: usize,
+    deposit: usize,
+    tokens: AtomicUsize,
+}
+
+impl RetryBudget {
+    /// Create a new budget holding at most `max_tokens` tokens, which starts full.
+    ///
+    /// Every successful call deposits `deposit_ratio` of a token, for example `0.1` allows one retry
+    /// for every 10 successful calls.
+    ///
+    /// Tokens are counted in an `AtomicUsize` to support targets without 64-bit atomics, so `max_tokens`
+    /// is capped at `usize::MAX / 1000`, which is about 4 million on 32-bit targets.
+    pub fn new(max_tokens: u32, deposit_ratio: f32) -> Self {
+        let max_tokens = usize::try_from(max_tokens)
+            .unwrap_or(usize::MAX)
+            .saturating_mul(SCALE);
+
+        Self {
+            max_tokens,
+            deposit: (deposit_ratio.max(0.0) * SCALE as f32) as usize,
+            tokens: AtomicUsize::new(max_tokens),
+        }
+    }
+
+    /// Return the number of whole tokens available.
+    pub fn available(&self) -> u32 {
+        u32::try_from(self.tokens.load(Ordering::Acquire) / SCALE).unwrap_or(u32::MAX)
+    }
+
+    /// Deposit tokens for a successful call.
+    pub fn deposit(&self) {
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                Some(tokens.saturating_add(self.deposit).min(self.max_tokens))
+            });
+    }
+
+    /// Withdraw a token for a retry, return `false` if the budget has been exhausted.
+    pub fn try_withdraw(&self) -> bool {
+        self.tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                tokens.checked_sub(SCALE)
+            })
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(2, 0.5);
+        assert_eq!(budget.available(), 2);
+
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+
+        budget.deposit();
+        assert_eq!(budget.available(), 0);
+        budget.deposit();
+        assert_eq!(budget.available(), 1);
+        assert!(budget.try_withdraw());
+
+        // Deposits never exceed the max tokens.
+        for _ in 0..10 {
+            budget.deposit();
+        }
+        assert_eq!(budget.available(), 2);
+    }
+}
@@ -0,0 +1,167 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+/// RetryAttempt records the error of a failed attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RetryAttempt<E> {
+    /// The error returned by the attempt.
+    pub error: E,
+    /// The time elapsed since the first attempt started when this attempt failed.
+    ///
+    /// It's always zero if no [`Clock`][crate::Clock] is available.
+    pub elapsed: Duration,
+    /// The delay slept after this attempt, or `None` if it's the last attempt.
+    pub delay: Option<Duration>,
+}
+
+/// RetryError is returned by [`Retry::collect_errors`][crate::Retry::collect_errors] once the retry gives up.
+///
+/// It holds the errors of all attempts in order, so the last one is the error that ended the retry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryError<E> {
+    attempts: Vec<RetryAttempt<E>>,
+}
+
+impl<E> RetryError<E> {
+    /// Return all failed attempts in order.
+    pub fn attempts(&self) -> &[RetryAttempt<E>] {
+        &self.attempts
+    }
+
+    /// Return the error of the first attempt.
+    pub fn first(&self) -> &E {
+        &self.attempts[0].error
+    }
+
+    /// Return the error of the last attempt, which ended the retry.
+    pub fn last(&self) -> &E {
+        &self.attempts[self.attempts.len() - 1].error
+    }
+
+    /// Consume the error and return the error of the last attempt.
+    pub fn into_last(mut self) -> E {
+        self.attempts
+            .pop()
+            .expect("retry error must have attempts")
+            .error
+    }
+
+    /// Consume the error and return all failed attempts in order.
+    pub fn into_attempts(self) -> Vec<RetryAttempt<E>> {
+        self.attempts
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "retry failed after {} attempts", self.attempts.len())?;
+        for (idx, attempt) in self.attempts.iter().enumerate() {
+            write!(
+                f,
+                "\n  attempt {} at {:?}: {}",
+                idx + 1,
+                attempt.elapsed,
+                attempt.error
+            )?;
+            if let Some(delay) = attempt.delay {
+                write!(f, " (retried after {:?})", delay)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The last error is already part of the summary printed by [`Display`][fmt::Display], so it's not
+/// returned as the source, which would print it twice in error reports.
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RetryError<E> {}
+
+/// A sink that decides what to do with the errors of failed attempts.
+#[doc(hidden)]
+pub trait RetryErrorSink<E> {
+    /// The error returned once the retry gives up.
+    type Error;
+
+    /// Record the error of an attempt that will be retried after `delay`.
+    fn record(&mut self, err: E, elapsed: Duration, delay: Duration);
+
+    /// Build the returned error from the error of the last attempt.
+    fn finish(&mut self, err: E, elapsed: Duration) -> Self::Error;
+}
+
+/// `()` drops the errors of retried attempts and returns the last error as is.
+impl<E> RetryErrorSink<E> for () {
+    type Error = E;
+
+    fn record(&mut self, _: E, _: Duration, _: Duration) {}
+
+    fn finish(&mut self, err: E, _: Duration) -> Self::Error {
+        err
+    }
+}
+
+/// ErrorCollector keeps the errors of all attempts to build a [`RetryError`].
+#[doc(hidden)]
+pub struct ErrorCollector<E>(Vec<RetryAttempt<E>>);
+
+impl<E> Default for ErrorCollector<E> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<E> RetryErrorSink<E> for ErrorCollector<E> {
+    type Error = RetryError<E>;
+
+    fn record(&mut self, error: E, elapsed: Duration, delay: Duration) {
+        self.0.push(RetryAttempt {
+            error,
+            elapsed,
+            delay: Some(delay),
+        });
+    }
+
+    fn finish(&mut self, error: E, elapsed: Duration) -> Self::Error {
+        let mut attempts = core::mem::take(&mut self.0);
+        attempts.push(RetryAttempt {
+            error,
+            elapsed,
+            delay: None,
+        });
+        RetryError { attempts }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_error() {
+        let mut sink = ErrorCollector::default();
+        sink.record("timeout", Duration::from_millis(10), Duration::from_secs(1));
+        let err = sink.finish("refused", Duration::from_millis(1020));
+
+        assert_eq!(err.attempts().len(), 2);
+        assert_eq!(*err.first(), "timeout");
+        assert_eq!(*err.last(), "refused");
+        assert_eq!(
+            err.to_string(),
+            "retry failed after 2 attempts\n  attempt 1 at 10ms: timeout (retried after 1s)\n  attempt 2 at 1.02s: refused"
+        );
+        assert_eq!(err.into_last(), "refused");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_retry_error_source() {
+        use std::error::Error;
+
+        let err = ErrorCollector::default().finish("refused", Duration::ZERO);
+        assert!(err.source().is_none());
+    }
+}
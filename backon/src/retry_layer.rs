@@ -0,0 +1,405 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::backoff::BackoffBuilder;
+use crate::sleep::MaybeSleeper;
+use crate::Backoff;
+use crate::DefaultSleeper;
+use crate::Sleeper;
+
+/// RetryLayer applies retries with backoff to a [`Service`].
+///
+/// Requests are cloned before every call so that they can be sent again. By default the request must be
+/// `Clone`, use [`RetryLayer::clone_request`] to provide a custom hook instead, for example for requests
+/// carrying a body that can only be replayed in some cases.
+///
+/// # Examples
+///
+/// ```no_run
+/// use backon::ExponentialBuilder;
+/// use backon::RetryLayer;
+/// use tower_layer::Layer;
+/// use tower_service::Service;
+///
+/// fn retry<S: Service<String, Error = std::io::Error> + Clone>(
+///     service: S,
+/// ) -> impl Service<String, Response = S::Response, Error = S::Error> {
+///     RetryLayer::new(ExponentialBuilder::default())
+///         .when(|e: &std::io::Error| e.kind() == std::io::ErrorKind::TimedOut)
+///         .layer(service)
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryLayer<
+    B: BackoffBuilder + Clone,
+    RF = (),
+    CF = (),
+    SF: MaybeSleeper = DefaultSleeper,
+> {
+    builder: B,
+    retryable: RF,
+    clone_request: CF,
+    sleep_fn: SF,
+}
+
+impl<B: BackoffBuilder + Clone> RetryLayer<B> {
+    /// Create a new retry layer which builds a backoff for every request.
+    pub fn new(builder: B) -> Self {
+        Self {
+            builder,
+            retryable: (),
+            clone_request: (),
+            sleep_fn: DefaultSleeper::default(),
+        }
+    }
+}
+
+impl<B, RF, CF, SF> RetryLayer<B, RF, CF, SF>
+where
+    B: BackoffBuilder + Clone,
+    SF: MaybeSleeper,
+{
+    /// Set the sleeper for retrying.
+    ///
+    /// The sleeper should implement the [`Sleeper`] trait and be `Clone` since it's shared by all services.
+    ///
+    /// If not specified, we use the [`DefaultSleeper`].
+    pub fn sleep<SN: Sleeper + Clone>(self, sleep_fn: SN) -> RetryLayer<B, RF, CF, SN> {
+        RetryLayer {
+            builder: self.builder,
+            retryable: self.retryable,
+            clone_request: self.clone_request,
+            sleep_fn,
+        }
+    }
+
+    /// Set the conditions for retrying.
+    ///
+    /// If not specified, all errors are considered retryable.
+    pub fn when<E, RN: Fn(&E) -> bool + Clone>(self, retryable: RN) -> RetryLayer<B, RN, CF, SF> {
+        RetryLayer {
+            builder: self.builder,
+            retryable,
+            clone_request: self.clone_request,
+            sleep_fn: self.sleep_fn,
+        }
+    }
+
+    /// Set the hook to clone requests before they are sent.
+    ///
+    /// Returning `None` means the request can't be sent again, so its error will be returned directly.
+    ///
+    /// If not specified, requests are cloned with `Clone`.
+    pub fn clone_request<Req, CN: Fn(&Req) -> Option<Req> + Clone>(
+        self,
+        clone_request: CN,
+    ) -> RetryLayer<B, RF, CN, SF> {
+        RetryLayer {
+            builder: self.builder,
+            retryable: self.retryable,
+            clone_request,
+            sleep_fn: self.sleep_fn,
+        }
+    }
+}
+
+impl<S, B, RF, CF, SF> Layer<S> for RetryLayer<B, RF, CF, SF>
+where
+    B: BackoffBuilder + Clone,
+    RF: Clone,
+    CF: Clone,
+    SF: MaybeSleeper + Clone,
+{
+    type Service = RetryService<S, B, RF, CF, SF>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryService {
+            inner,
+            builder: self.builder.clone(),
+            retryable: self.retryable.clone(),
+            clone_request: self.clone_request.clone(),
+            sleep_fn: self.sleep_fn.clone(),
+        }
+    }
+}
+
+/// Service generated by [`RetryLayer`].
+#[derive(Debug, Clone)]
+pub struct RetryService<
+    S,
+    B: BackoffBuilder + Clone,
+    RF = (),
+    CF = (),
+    SF: MaybeSleeper = DefaultSleeper,
+> {
+    inner: S,
+    builder: B,
+    retryable: RF,
+    clone_request: CF,
+    sleep_fn: SF,
+}
+
+impl<S, B, RF, CF, SF, Req> Service<Req> for RetryService<S, B, RF, CF, SF>
+where
+    S: Service<Req> + Clone,
+    B: BackoffBuilder + Clone,
+    RF: MaybeRetryable<S::Error> + Clone,
+    CF: MaybeCloneRequest<Req> + Clone,
+    SF: Sleeper + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RetryServiceFuture<S, Req, B::Backoff, RF, CF, SF>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        // The service which has been driven to readiness must be the one handling this call.
+        let clone = self.inner.clone();
+        let mut service = core::mem::replace(&mut self.inner, clone);
+
+        let pending = self.clone_request.clone_request(&req);
+        RetryServiceFuture {
+            backoff: self.builder.clone().build(),
+            retryable: self.retryable.clone(),
+            clone_request: self.clone_request.clone(),
+            sleep_fn: self.sleep_fn.clone(),
+            pending,
+            state: State::Calling(service.call(req)),
+            service,
+        }
+    }
+}
+
+/// Future generated by [`RetryService`].
+pub struct RetryServiceFuture<S: Service<Req>, Req, B, RF, CF, SF: Sleeper> {
+    service: S,
+    backoff: B,
+    retryable: RF,
+    clone_request: CF,
+    sleep_fn: SF,
+    pending: Option<Req>,
+
+    state: State<S::Future, SF::Sleep>,
+}
+
+/// State maintains internal state of retry.
+enum State<Fut, SleepFut> {
+    Calling(Fut),
+    Sleeping(SleepFut),
+    Waiting,
+}
+
+impl<S, Req, B, RF, CF, SF> Future for RetryServiceFuture<S, Req, B, RF, CF, SF>
+where
+    S: Service<Req>,
+    B: Backoff,
+    RF: MaybeRetryable<S::Error>,
+    CF: MaybeCloneRequest<Req>,
+    SF: Sleeper,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `RetryServiceFuture` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        loop {
+            match &mut this.state {
+                State::Calling(fut) => {
+                    // Safety: This is safe because we don't move the `RetryServiceFuture` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let fut = unsafe { Pin::new_unchecked(fut) };
+
+                    let err = match ready!(fut.poll(cx)) {
+                        Ok(resp) => return Poll::Ready(Ok(resp)),
+                        Err(err) => err,
+                    };
+                    // If the request can't be sent again or input error is not retryable, return error directly.
+                    if this.pending.is_none() || !this.retryable.retryable(&err) {
+                        return Poll::Ready(Err(err));
+                    }
+                    match this.backoff.next() {
+                        None => return Poll::Ready(Err(err)),
+                        Some(dur) => {
+                            this.state = State::Sleeping(this.sleep_fn.sleep(dur));
+                            continue;
+                        }
+                    }
+                }
+                State::Sleeping(sl) => {
+                    // Safety: This is safe because we don't move the `RetryServiceFuture` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let sl = unsafe { Pin::new_unchecked(sl) };
+
+                    ready!(sl.poll(cx));
+                    this.state = State::Waiting;
+                    continue;
+                }
+                State::Waiting => {
+                    ready!(this.service.poll_ready(cx))?;
+
+                    let req = this
+                        .pending
+                        .take()
+                        .expect("request must be cloned before retrying");
+                    this.pending = this.clone_request.clone_request(&req);
+                    this.state = State::Calling(this.service.call(req));
+                    continue;
+                }
+            }
+        }
+    }
+}
+
+/// A stub trait allowing the default `()` to be used as a classifier in [`RetryLayer`] that retries all errors.
+#[doc(hidden)]
+pub trait MaybeRetryable<E> {
+    fn retryable(&self, err: &E) -> bool;
+}
+
+impl<E> MaybeRetryable<E> for () {
+    fn retryable(&self, _: &E) -> bool {
+        true
+    }
+}
+
+impl<E, F: Fn(&E) -> bool> MaybeRetryable<E> for F {
+    fn retryable(&self, err: &E) -> bool {
+        self(err)
+    }
+}
+
+/// A stub trait allowing the default `()` to be used as a hook in [`RetryLayer`] that clones requests with `Clone`.
+#[doc(hidden)]
+pub trait MaybeCloneRequest<Req> {
+    fn clone_request(&self, req: &Req) -> Option<Req>;
+}
+
+impl<Req: Clone> MaybeCloneRequest<Req> for () {
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        Some(req.clone())
+    }
+}
+
+impl<Req, F: Fn(&Req) -> Option<Req>> MaybeCloneRequest<Req> for F {
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        self(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use core::future::poll_fn;
+    use core::future::ready;
+    use core::future::Ready;
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    /// A service failing with its call count until `succeed_at` calls were made.
+    #[derive(Clone)]
+    struct Flaky {
+        calls: Arc<AtomicUsize>,
+        succeed_at: usize,
+    }
+
+    impl Service<u32> for Flaky {
+        type Response = u32;
+        type Error = usize;
+        type Future = Ready<Result<u32, usize>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), usize>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: u32) -> Self::Future {
+            let call = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+            if call >= self.succeed_at {
+                ready(Ok(req))
+            } else {
+                ready(Err(call))
+            }
+        }
+    }
+
+    async fn call<S: Service<u32>>(service: &mut S, req: u32) -> Result<S::Response, S::Error> {
+        poll_fn(|cx| service.poll_ready(cx)).await?;
+        service.call(req).await
+    }
+
+    fn flaky(succeed_at: usize) -> (Flaky, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = Flaky {
+            calls: calls.clone(),
+            succeed_at,
+        };
+        (service, calls)
+    }
+
+    #[test]
+    async fn test_retry_layer() {
+        let (service, calls) = flaky(3);
+
+        let mut service = RetryLayer::new(ConstantBuilder::default())
+            .sleep(|_: Duration| ready(()))
+            .layer(service);
+
+        assert_eq!(call(&mut service, 42).await, Ok(42));
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    async fn test_retry_layer_with_not_retryable_error() {
+        let (service, calls) = flaky(usize::MAX);
+
+        let mut service = RetryLayer::new(ConstantBuilder::default())
+            .sleep(|_: Duration| ready(()))
+            .when(|e: &usize| *e < 2)
+            .layer(service);
+
+        assert_eq!(call(&mut service, 42).await, Err(2));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    async fn test_retry_layer_with_clone_request() {
+        let (service, calls) = flaky(usize::MAX);
+
+        // Only even requests can be sent again.
+        let mut service = RetryLayer::new(ConstantBuilder::default().with_max_times(2))
+            .sleep(|_: Duration| ready(()))
+            .clone_request(|req: &u32| (req % 2 == 0).then_some(*req))
+            .layer(service);
+
+        assert_eq!(call(&mut service, 1).await, Err(1));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(call(&mut service, 2).await, Err(4));
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+    }
+}
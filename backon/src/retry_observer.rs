@@ -0,0 +1,193 @@
+use alloc::sync::Arc;
+use core::time::Duration;
+
+/// RetryObserver observes every step of a retry, including its final outcome.
+///
+/// Unlike `notify` which is only called before retrying, an observer also learns about every attempt
+/// and whether the retry succeeded or gave up in the end, which makes it a good fit for exporting metrics.
+/// All methods do nothing by default, so only the interesting ones need to be implemented.
+///
+/// Observers take `&self` so they can be shared by many retries, either by reference or through an [`Arc`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::sync::atomic::AtomicUsize;
+/// use core::sync::atomic::Ordering;
+///
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryObserver;
+/// use backon::Retryable;
+///
+/// #[derive(Default)]
+/// struct GiveUps(AtomicUsize);
+///
+/// impl<E> RetryObserver<E> for GiveUps {
+///     fn on_give_up(&self, _: &E, _: usize) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let give_ups = GiveUps::default();
+///
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .observe(&give_ups)
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+pub trait RetryObserver<E> {
+    /// Called when an attempt is started, `attempt` starts from 1.
+    fn on_attempt(&self, attempt: usize) {
+        let _ = attempt;
+    }
+
+    /// Called when an attempt failed and will be retried after `dur`.
+    fn on_retry(&self, err: &E, dur: Duration) {
+        let _ = (err, dur);
+    }
+
+    /// Called when the retry succeeded after `attempts` attempts.
+    fn on_success(&self, attempts: usize) {
+        let _ = attempts;
+    }
+
+    /// Called when the retry gave up with the error after `attempts` attempts.
+    fn on_give_up(&self, err: &E, attempts: usize) {
+        let _ = (err, attempts);
+    }
+}
+
+/// `()` observes nothing.
+impl<E> RetryObserver<E> for () {}
+
+impl<E, O: RetryObserver<E> + ?Sized> RetryObserver<E> for &O {
+    fn on_attempt(&self, attempt: usize) {
+        (**self).on_attempt(attempt)
+    }
+
+    fn on_retry(&self, err: &E, dur: Duration) {
+        (**self).on_retry(err, dur)
+    }
+
+    fn on_success(&self, attempts: usize) {
+        (**self).on_success(attempts)
+    }
+
+    fn on_give_up(&self, err: &E, attempts: usize) {
+        (**self).on_give_up(err, attempts)
+    }
+}
+
+impl<E, O: RetryObserver<E> + ?Sized> RetryObserver<E> for Arc<O> {
+    fn on_attempt(&self, attempt: usize) {
+        (**self).on_attempt(attempt)
+    }
+
+    fn on_retry(&self, err: &E, dur: Duration) {
+        (**self).on_retry(err, dur)
+    }
+
+    fn on_success(&self, attempts: usize) {
+        (**self).on_success(attempts)
+    }
+
+    fn on_give_up(&self, err: &E, attempts: usize) {
+        (**self).on_give_up(err, attempts)
+    }
+}
+
+/// MetricsObserver records retries with the [`metrics`] crate.
+///
+/// All metrics are labeled with `operation`, so that retries of different calls can be told apart:
+///
+/// - `backon_attempts_total`: counter of started attempts.
+/// - `backon_retries_total`: counter of failed attempts which will be retried.
+/// - `backon_retry_delay_seconds`: histogram of the delays before retrying.
+/// - `backon_successes_total`: counter of succeeded retries.
+/// - `backon_give_ups_total`: counter of retries which gave up.
+/// - `backon_attempts_per_call`: histogram of the attempts made by finished retries.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::MetricsObserver;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .observe(MetricsObserver::new("fetch"))
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone)]
+pub struct MetricsObserver {
+    operation: metrics::SharedString,
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsObserver {
+    /// Create a new observer recording metrics labeled with `operation`.
+    pub fn new(operation: impl Into<metrics::SharedString>) -> Self {
+        Self {
+            operation: operation.into(),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl<E> RetryObserver<E> for MetricsObserver {
+    fn on_attempt(&self, _: usize) {
+        metrics::counter!("backon_attempts_total", "operation" => self.operation.clone())
+            .increment(1);
+    }
+
+    fn on_retry(&self, _: &E, dur: Duration) {
+        metrics::counter!("backon_retries_total", "operation" => self.operation.clone())
+            .increment(1);
+        metrics::histogram!("backon_retry_delay_seconds", "operation" => self.operation.clone())
+            .record(dur.as_secs_f64());
+    }
+
+    fn on_success(&self, attempts: usize) {
+        metrics::counter!("backon_successes_total", "operation" => self.operation.clone())
+            .increment(1);
+        metrics::histogram!("backon_attempts_per_call", "operation" => self.operation.clone())
+            .record(attempts as f64);
+    }
+
+    fn on_give_up(&self, _: &E, attempts: usize) {
+        metrics::counter!("backon_give_ups_total", "operation" => self.operation.clone())
+            .increment(1);
+        metrics::histogram!("backon_attempts_per_call", "operation" => self.operation.clone())
+            .record(attempts as f64);
+    }
+}
@@ -0,0 +1,286 @@
+use core::future::Future;
+use core::future::Ready;
+use core::time::Duration;
+
+use crate::clock::MaybeClock;
+
+/// RetryState describes the progress of a retry when an attempt failed.
+///
+/// It's passed to the functions set by `when_with_state` and `notify_with_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RetryState {
+    /// The number of the attempt that just failed, starting from `1`.
+    pub attempt: usize,
+    /// The time elapsed since the first attempt started.
+    ///
+    /// It's always zero if no [`Clock`][crate::Clock] is available.
+    pub elapsed: Duration,
+    /// The total time slept between all previous attempts.
+    pub total_slept: Duration,
+    /// The delay before the next attempt.
+    ///
+    /// It's `None` while deciding whether to retry, since the backoff hasn't been consulted yet.
+    pub next_delay: Option<Duration>,
+}
+
+/// RetryStats describes how a retry went once it finished.
+///
+/// It's returned alongside the result by `with_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RetryStats {
+    /// The number of attempts made, starting from `1`.
+    pub attempts: usize,
+    /// The total time slept between attempts.
+    pub total_slept: Duration,
+    /// The time elapsed since the first attempt started.
+    ///
+    /// It's always zero if no [`Clock`][crate::Clock] is available.
+    pub elapsed: Duration,
+    /// Whether the result came from the first attempt.
+    pub first_attempt: bool,
+}
+
+/// RetryProgress tracks the progress of a retry to build [`RetryState`].
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RetryProgress {
+    attempts: usize,
+    started_at: Option<Duration>,
+    total_slept: Duration,
+    #[cfg(feature = "tracing")]
+    span: Option<tracing::Span>,
+}
+
+impl RetryProgress {
+    /// Record that a new attempt is started.
+    pub(crate) fn start_attempt(&mut self, clock: &impl MaybeClock) {
+        if self.attempts == 0 {
+            self.started_at = clock.try_now();
+        }
+        self.attempts += 1;
+    }
+
+    /// Return true if an attempt has been started.
+    pub(crate) fn is_started(&self) -> bool {
+        self.attempts > 0
+    }
+
+    /// Record that the retry is going to sleep before the next attempt.
+    pub(crate) fn sleep(&mut self, dur: Duration) {
+        self.total_slept = self.total_slept.saturating_add(dur);
+    }
+
+    /// Build the state of the current attempt.
+    pub(crate) fn state(
+        &self,
+        clock: &impl MaybeClock,
+        next_delay: Option<Duration>,
+    ) -> RetryState {
+        let elapsed = match (self.started_at, clock.try_now()) {
+            (Some(started_at), Some(now)) => now.saturating_sub(started_at),
+            _ => Duration::ZERO,
+        };
+
+        RetryState {
+            attempt: self.attempts,
+            elapsed,
+            total_slept: self.total_slept,
+            next_delay,
+        }
+    }
+
+    /// Return the span covering all attempts of the retry, which is created on first use.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn span(&mut self) -> tracing::Span {
+        self.span
+            .get_or_insert_with(crate::trace::retry_span)
+            .clone()
+    }
+
+    /// Build the stats of the finished retry.
+    pub(crate) fn stats(&self, clock: &impl MaybeClock) -> RetryStats {
+        let state = self.state(clock, None);
+
+        RetryStats {
+            attempts: state.attempt,
+            total_slept: state.total_slept,
+            elapsed: state.elapsed,
+            first_attempt: state.attempt == 1,
+        }
+    }
+}
+
+/// An output that decides whether [`RetryStats`] will be returned alongside the result.
+#[doc(hidden)]
+pub trait StatsOutput<R> {
+    /// The output of the retry.
+    type Output;
+
+    /// Build the output from the result and the stats of the retry.
+    fn output(result: R, stats: RetryStats) -> Self::Output;
+}
+
+/// `()` returns the result as is.
+impl<R> StatsOutput<R> for () {
+    type Output = R;
+
+    fn output(result: R, _: RetryStats) -> Self::Output {
+        result
+    }
+}
+
+/// CollectStats returns the result alongside the [`RetryStats`].
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollectStats;
+
+impl<R> StatsOutput<R> for CollectStats {
+    type Output = (R, RetryStats);
+
+    fn output(result: R, stats: RetryStats) -> Self::Output {
+        (result, stats)
+    }
+}
+
+/// A condition that decides whether an error should be retried.
+///
+/// It's implemented for all `FnMut(&E) -> bool` and for functions set by `when_with_state`.
+#[doc(hidden)]
+pub trait RetryCondition<E> {
+    /// Return `true` if the error should be retried.
+    fn should_retry(&mut self, err: &E, state: &RetryState) -> bool;
+}
+
+/// All `FnMut(&E) -> bool` implements `RetryCondition`.
+impl<E, F: FnMut(&E) -> bool> RetryCondition<E> for F {
+    fn should_retry(&mut self, err: &E, _: &RetryState) -> bool {
+        self(err)
+    }
+}
+
+/// A notifier that is invoked before sleeping for the next attempt.
+///
+/// It's implemented for all `FnMut(&E, Duration)` and for functions set by `notify_with_state`.
+#[doc(hidden)]
+pub trait RetryNotify<E> {
+    /// Notify that the error will be retried after the given duration.
+    fn notify(&mut self, err: &E, dur: Duration, state: &RetryState);
+}
+
+/// All `FnMut(&E, Duration)` implements `RetryNotify`.
+impl<E, F: FnMut(&E, Duration)> RetryNotify<E> for F {
+    fn notify(&mut self, err: &E, dur: Duration, _: &RetryState) {
+        self(err, dur)
+    }
+}
+
+/// A wrapper for functions that accept [`RetryState`] as well.
+#[doc(hidden)]
+pub struct WithState<F>(pub(crate) F);
+
+/// All `FnMut(&E, &RetryState) -> bool` implements `RetryCondition` by wrapping with `WithState`.
+impl<E, F: FnMut(&E, &RetryState) -> bool> RetryCondition<E> for WithState<F> {
+    fn should_retry(&mut self, err: &E, state: &RetryState) -> bool {
+        (self.0)(err, state)
+    }
+}
+
+/// All `FnMut(&E, Duration, &RetryState)` implements `RetryNotify` by wrapping with `WithState`.
+impl<E, F: FnMut(&E, Duration, &RetryState)> RetryNotify<E> for WithState<F> {
+    fn notify(&mut self, err: &E, dur: Duration, state: &RetryState) {
+        (self.0)(err, dur, state)
+    }
+}
+
+/// An asynchronous condition that decides whether an error should be retried.
+///
+/// It's implemented for everything that implements [`RetryCondition`] and for functions set by `when_async`.
+#[doc(hidden)]
+pub trait AsyncRetryCondition<E> {
+    /// The future returned by `should_retry`.
+    type Future: Future<Output = bool>;
+
+    /// Return a future that resolves to `true` if the error should be retried.
+    fn should_retry(&mut self, err: &E, state: &RetryState) -> Self::Future;
+}
+
+/// All `FnMut(&E) -> bool` implements `AsyncRetryCondition` by resolving immediately.
+impl<E, F: FnMut(&E) -> bool> AsyncRetryCondition<E> for F {
+    type Future = Ready<bool>;
+
+    fn should_retry(&mut self, err: &E, state: &RetryState) -> Self::Future {
+        core::future::ready(RetryCondition::should_retry(self, err, state))
+    }
+}
+
+/// All `FnMut(&E, &RetryState) -> bool` implements `AsyncRetryCondition` by resolving immediately.
+impl<E, F: FnMut(&E, &RetryState) -> bool> AsyncRetryCondition<E> for WithState<F> {
+    type Future = Ready<bool>;
+
+    fn should_retry(&mut self, err: &E, state: &RetryState) -> Self::Future {
+        core::future::ready(RetryCondition::should_retry(self, err, state))
+    }
+}
+
+/// An asynchronous notifier that is invoked before sleeping for the next attempt.
+///
+/// It's implemented for everything that implements [`RetryNotify`] and for functions set by `notify_async`.
+#[doc(hidden)]
+pub trait AsyncRetryNotify<E> {
+    /// The future returned by `notify`.
+    type Future: Future<Output = ()>;
+
+    /// Return a future that notifies that the error will be retried after the given duration.
+    fn notify(&mut self, err: &E, dur: Duration, state: &RetryState) -> Self::Future;
+}
+
+/// All `FnMut(&E, Duration)` implements `AsyncRetryNotify` by resolving immediately.
+impl<E, F: FnMut(&E, Duration)> AsyncRetryNotify<E> for F {
+    type Future = Ready<()>;
+
+    fn notify(&mut self, err: &E, dur: Duration, state: &RetryState) -> Self::Future {
+        RetryNotify::notify(self, err, dur, state);
+        core::future::ready(())
+    }
+}
+
+/// All `FnMut(&E, Duration, &RetryState)` implements `AsyncRetryNotify` by resolving immediately.
+impl<E, F: FnMut(&E, Duration, &RetryState)> AsyncRetryNotify<E> for WithState<F> {
+    type Future = Ready<()>;
+
+    fn notify(&mut self, err: &E, dur: Duration, state: &RetryState) -> Self::Future {
+        RetryNotify::notify(self, err, dur, state);
+        core::future::ready(())
+    }
+}
+
+/// A wrapper for functions that return futures.
+#[doc(hidden)]
+pub struct AsyncHook<F>(pub(crate) F);
+
+/// All `FnMut(&E) -> impl Future<Output = bool>` implements `AsyncRetryCondition` by wrapping with `AsyncHook`.
+impl<E, F, Fut> AsyncRetryCondition<E> for AsyncHook<F>
+where
+    F: FnMut(&E) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    type Future = Fut;
+
+    fn should_retry(&mut self, err: &E, _: &RetryState) -> Self::Future {
+        (self.0)(err)
+    }
+}
+
+/// All `FnMut(&E, Duration) -> impl Future<Output = ()>` implements `AsyncRetryNotify` by wrapping with `AsyncHook`.
+impl<E, F, Fut> AsyncRetryNotify<E> for AsyncHook<F>
+where
+    F: FnMut(&E, Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    type Future = Fut;
+
+    fn notify(&mut self, err: &E, dur: Duration, _: &RetryState) -> Self::Future {
+        (self.0)(err, dur)
+    }
+}
@@ -6,9 +6,20 @@ use core::task::Poll;
 use core::time::Duration;
 
 use crate::backoff::BackoffBuilder;
+use crate::clock::resolve_deadline;
+use crate::clock::MaybeClock;
+use crate::retry_state::CollectStats;
+use crate::retry_state::RetryCondition;
+use crate::retry_state::RetryNotify;
+use crate::retry_state::RetryProgress;
+use crate::retry_state::StatsOutput;
+use crate::retry_state::WithState;
 use crate::sleep::MaybeSleeper;
 use crate::Backoff;
+use crate::Clock;
+use crate::DefaultClock;
 use crate::DefaultSleeper;
+use crate::RetryState;
 use crate::Sleeper;
 
 /// `RetryableWithContext` adds retry support for functions that produce futures with results
@@ -110,12 +121,19 @@ pub struct RetryWithContext<
     SF: MaybeSleeper = DefaultSleeper,
     RF = fn(&E) -> bool,
     NF = fn(&E, Duration),
+    CF: MaybeClock = DefaultClock,
+    ST = (),
 > {
     backoff: B,
     retryable: RF,
     notify: NF,
     future_fn: FutureFn,
     sleep_fn: SF,
+    clock: CF,
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    progress: RetryProgress,
+    stats: ST,
 
     state: State<T, E, Ctx, Fut, SF::Sleep>,
 }
@@ -134,20 +152,27 @@ where
             notify: |_: &E, _: Duration| {},
             future_fn,
             sleep_fn: DefaultSleeper::default(),
+            clock: DefaultClock::default(),
+            timeout: None,
+            deadline: None,
+            progress: RetryProgress::default(),
+            stats: (),
             state: State::Idle(None),
         }
     }
 }
 
-impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF>
-    RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF>
+#[allow(clippy::type_complexity)]
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, ST>
+    RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, ST>
 where
     B: Backoff,
     Fut: Future<Output = (Ctx, Result<T, E>)>,
     FutureFn: FnMut(Ctx) -> Fut,
     SF: Sleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
 {
     /// Set the sleeper for retrying.
     ///
@@ -157,7 +182,7 @@ where
     pub fn sleep<SN: Sleeper>(
         self,
         sleep_fn: SN,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SN, RF, NF> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SN, RF, NF, CF, ST> {
         assert!(
             matches!(self.state, State::Idle(None)),
             "sleep must be set before context"
@@ -169,6 +194,11 @@ where
             notify: self.notify,
             future_fn: self.future_fn,
             sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
             state: State::Idle(None),
         }
     }
@@ -179,13 +209,18 @@ where
     pub fn context(
         self,
         context: Ctx,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, ST> {
         RetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
             state: State::Idle(Some(context)),
         }
     }
@@ -222,13 +257,18 @@ where
     pub fn when<RN: FnMut(&E) -> bool>(
         self,
         retryable: RN,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RN, NF> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RN, NF, CF, ST> {
         RetryWithContext {
             backoff: self.backoff,
             retryable,
             notify: self.notify,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
             state: self.state,
         }
     }
@@ -271,16 +311,149 @@ where
     pub fn notify<NN: FnMut(&E, Duration)>(
         self,
         notify: NN,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NN> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NN, CF, ST> {
         RetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
             state: self.state,
         }
     }
+
+    /// Set the conditions for retrying with the state of the retry.
+    ///
+    /// This is the same as `when`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    /// `RetryState::next_delay` is always `None` here, since the backoff hasn't been consulted yet.
+    ///
+    /// If not specified, all errors are considered retryable.
+    pub fn when_with_state<RN: FnMut(&E, &RetryState) -> bool>(
+        self,
+        retryable: RN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, WithState<RN>, NF, CF, ST> {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: WithState(retryable),
+            notify: self.notify,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the state of the retry.
+    ///
+    /// This is the same as `notify`, but the input function will also receive a [`RetryState`],
+    /// which describes the failed attempt and how long the retry has been running.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn notify_with_state<NN: FnMut(&E, Duration, &RetryState)>(
+        self,
+        notify: NN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, WithState<NN>, CF, ST> {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: WithState(notify),
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
+            state: self.state,
+        }
+    }
+
+    /// Return the [`RetryStats`][crate::RetryStats] alongside the result.
+    ///
+    /// After calling this, the retry returns `((ctx, result), stats)` instead of just `(ctx, result)`, which makes
+    /// it easy to report how many attempts and how much time a call took without counting inside `notify`.
+    pub fn with_stats(
+        self,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, CollectStats> {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock: self.clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: CollectStats,
+            state: self.state,
+        }
+    }
+
+    /// Set the clock for retrying.
+    ///
+    /// The clock should implement the [`Clock`] trait. The simplest way is to use a closure like `Fn() -> Duration`.
+    ///
+    /// If not specified, we use the [`DefaultClock`]. The clock is only used to honor
+    /// [`RetryWithContext::timeout`] and [`RetryWithContext::deadline`].
+    pub fn clock<CN: Clock>(
+        self,
+        clock: CN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CN, ST> {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            clock,
+            timeout: self.timeout,
+            deadline: self.deadline,
+            progress: self.progress,
+            stats: self.stats,
+            state: self.state,
+        }
+    }
+
+    /// Set the timeout for the whole retry.
+    ///
+    /// The timeout starts when the retry is polled for the first time. If the next sleep would end
+    /// after the timeout elapsed, the last error will be returned directly instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    pub fn timeout(mut self, timeout: Duration) -> Self
+    where
+        CF: Clock,
+    {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the deadline for the whole retry.
+    ///
+    /// If the next sleep would end after the deadline, the last error will be returned directly
+    /// instead of sleeping.
+    ///
+    /// Unlike the total delay of a backoff, the time spent inside the function itself is counted as well.
+    /// If a timeout is set too, the retry stops at whichever comes first.
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn deadline(mut self, deadline: std::time::Instant) -> Self
+    where
+        CF: Clock,
+    {
+        self.deadline = Some(crate::clock::instant_to_deadline(deadline));
+        self
+    }
 }
 
 /// State maintains internal state of retry.
@@ -290,19 +463,39 @@ enum State<T, E, Ctx, Fut: Future<Output = (Ctx, Result<T, E>)>, SleepFut: Futur
     Sleeping((Option<Ctx>, SleepFut)),
 }
 
-impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF> Future
-    for RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF>
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, ST> Future
+    for RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, ST>
 where
     B: Backoff,
     Fut: Future<Output = (Ctx, Result<T, E>)>,
     FutureFn: FnMut(Ctx) -> Fut,
     SF: Sleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
+    ST: StatsOutput<(Ctx, Result<T, E>)>,
 {
-    type Output = (Ctx, Result<T, E>);
+    type Output = ST::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = ready!(self.as_mut().poll_result(cx));
+        Poll::Ready(ST::output(res, self.progress.stats(&self.clock)))
+    }
+}
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, ST>
+    RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, CF, ST>
+where
+    B: Backoff,
+    Fut: Future<Output = (Ctx, Result<T, E>)>,
+    FutureFn: FnMut(Ctx) -> Fut,
+    SF: Sleeper,
+    RF: RetryCondition<E>,
+    NF: RetryNotify<E>,
+    CF: MaybeClock,
+{
+    /// Poll the retry until it returns the result of the last attempt.
+    fn poll_result(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<(Ctx, Result<T, E>)> {
         // Safety: This is safe because we don't move the `Retry` struct itself,
         // only its internal state.
         //
@@ -312,6 +505,12 @@ where
         loop {
             match &mut this.state {
                 State::Idle(ctx) => {
+                    // The timeout starts with the first attempt, where the deadline is moved onto the clock as well.
+                    if !this.progress.is_started() {
+                        this.deadline = resolve_deadline(&this.clock, this.timeout, this.deadline);
+                    }
+
+                    this.progress.start_attempt(&this.clock);
                     let ctx = ctx.take().expect("context must be valid");
                     let fut = (this.future_fn)(ctx);
                     this.state = State::Polling(fut);
@@ -329,13 +528,30 @@ where
                         Ok(v) => return Poll::Ready((ctx, Ok(v))),
                         Err(err) => {
                             // If input error is not retryable, return error directly.
-                            if !(this.retryable)(&err) {
+                            if !this
+                                .retryable
+                                .should_retry(&err, &this.progress.state(&this.clock, None))
+                            {
                                 return Poll::Ready((ctx, Err(err)));
                             }
                             match this.backoff.next() {
                                 None => return Poll::Ready((ctx, Err(err))),
                                 Some(dur) => {
-                                    (this.notify)(&err, dur);
+                                    // If the next attempt would start after the deadline, return error directly.
+                                    if let (Some(deadline), Some(now)) =
+                                        (this.deadline, this.clock.try_now())
+                                    {
+                                        if now.saturating_add(dur) > deadline {
+                                            return Poll::Ready((ctx, Err(err)));
+                                        }
+                                    }
+
+                                    this.notify.notify(
+                                        &err,
+                                        dur,
+                                        &this.progress.state(&this.clock, Some(dur)),
+                                    );
+                                    this.progress.sleep(dur);
                                     this.state =
                                         State::Sleeping((Some(ctx), this.sleep_fn.sleep(dur)));
                                     continue;
@@ -365,7 +581,10 @@ where
 #[cfg(any(feature = "tokio-sleep", feature = "gloo-timers-sleep"))]
 mod tests {
     use alloc::string::ToString;
+    use alloc::sync::Arc;
     use anyhow::{anyhow, Result};
+    use core::future::ready;
+    use core::sync::atomic::{AtomicU64, Ordering};
     use core::time::Duration;
     use tokio::sync::Mutex;
 
@@ -376,6 +595,7 @@ mod tests {
     use tokio::test;
 
     use super::*;
+    use crate::ConstantBuilder;
     use crate::ExponentialBuilder;
 
     struct Test;
@@ -415,4 +635,119 @@ mod tests {
         // only once.
         assert_eq!(*error_times.lock().await, 1);
     }
+
+    #[test]
+    async fn test_retry_with_stats() {
+        let test = Test;
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let ((_, result), stats) = {
+            |mut v: Test| async {
+                let res = v.hello().await;
+                (v, res)
+            }
+        }
+        .retry(backoff)
+        .context(test)
+        .with_stats()
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(stats.attempts, 4);
+        assert_eq!(stats.total_slept, Duration::from_millis(7));
+        assert!(!stats.first_attempt);
+    }
+
+    #[test]
+    async fn test_retry_with_state() {
+        let test = Test;
+
+        let mut notified = 0;
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let (_, result) = {
+            |mut v: Test| async {
+                let res = v.hello().await;
+                (v, res)
+            }
+        }
+        .retry(backoff)
+        .context(test)
+        .when_with_state(|_, state| state.attempt < 2)
+        .notify_with_state(|_, _, state| {
+            assert_eq!(1, state.attempt);
+            notified += 1;
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(1, notified);
+    }
+
+    #[test]
+    async fn test_retry_with_timeout() {
+        let now = Arc::new(AtomicU64::new(0));
+        let error_times = Mutex::new(0);
+
+        let test = Test;
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let (_, result) = {
+            |mut v: Test| async {
+                *error_times.lock().await += 1;
+                now.fetch_add(1000, Ordering::Relaxed);
+
+                let res = v.hello().await;
+                (v, res)
+            }
+        }
+        .retry(ConstantBuilder::default().without_max_times())
+        .sleep(move |dur: Duration| {
+            sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+            ready(())
+        })
+        .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+        .timeout(Duration::from_millis(3500))
+        .context(test)
+        .await;
+
+        assert!(result.is_err());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(*error_times.lock().await, 2);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    async fn test_retry_with_deadline() {
+        let now = Arc::new(AtomicU64::new(0));
+        let error_times = Mutex::new(0);
+
+        let test = Test;
+
+        let clock_now = now.clone();
+        let sleep_now = now.clone();
+        let (_, result) = {
+            |mut v: Test| async {
+                *error_times.lock().await += 1;
+                now.fetch_add(1000, Ordering::Relaxed);
+
+                let res = v.hello().await;
+                (v, res)
+            }
+        }
+        .retry(ConstantBuilder::default().without_max_times())
+        .sleep(move |dur: Duration| {
+            sleep_now.fetch_add(dur.as_millis() as u64, Ordering::Relaxed);
+            ready(())
+        })
+        // The deadline can be set before the clock.
+        .deadline(std::time::Instant::now() + Duration::from_millis(3500))
+        .clock(move || Duration::from_millis(clock_now.load(Ordering::Relaxed)))
+        .context(test)
+        .await;
+
+        assert!(result.is_err());
+        // Attempts start at 0s and 2s, the third one would start at 4s.
+        assert_eq!(*error_times.lock().await, 2);
+    }
 }
@@ -0,0 +1,398 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+use core::time::Duration;
+
+use futures_core::Stream;
+
+use crate::backoff::BackoffBuilder;
+use crate::sleep::MaybeSleeper;
+use crate::DefaultSleeper;
+use crate::Sleeper;
+
+/// StreamRetryable adds retry support for functions that produce streams of results.
+///
+/// This means all types implementing `FnMut() -> impl Stream<Item = Result<T, E>>` can use `retry`.
+///
+/// When the stream yields an error, it will be dropped and created again after sleeping according to
+/// the backoff, so it fits long-lived watch or subscription streams. The stream ends once it ends by
+/// itself, or when an error can't be retried anymore, in which case the error is yielded as the last item.
+///
+/// The builder must be `Clone` since the backoff could be built again, see [`StreamRetry::reset_after`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::pin::pin;
+///
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::StreamRetryable;
+/// use futures_util::stream;
+/// use futures_util::Stream;
+/// use futures_util::StreamExt;
+///
+/// fn watch() -> impl Stream<Item = Result<String>> {
+///     stream::iter(vec![Ok("event".to_string()), Err(anyhow::anyhow!("disconnected"))])
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let mut events = pin!(watch.retry(ExponentialBuilder::default()));
+///     while let Some(event) = events.next().await {
+///         println!("got event: {}", event?);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub trait StreamRetryable<
+    B: BackoffBuilder + Clone,
+    T,
+    E,
+    St: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> St,
+>
+{
+    /// Generate a new retry
+    fn retry(self, builder: B) -> StreamRetry<B, T, E, St, StreamFn>;
+}
+
+impl<B, T, E, St, StreamFn> StreamRetryable<B, T, E, St, StreamFn> for StreamFn
+where
+    B: BackoffBuilder + Clone,
+    St: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> St,
+{
+    fn retry(self, builder: B) -> StreamRetry<B, T, E, St, StreamFn> {
+        StreamRetry::new(self, builder)
+    }
+}
+
+/// Stream generated by [`StreamRetryable`].
+pub struct StreamRetry<
+    B: BackoffBuilder + Clone,
+    T,
+    E,
+    St: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> St,
+    SF: MaybeSleeper = DefaultSleeper,
+    RF = fn(&E) -> bool,
+    NF = fn(&E, Duration),
+> {
+    builder: B,
+    backoff: B::Backoff,
+    retryable: RF,
+    notify: NF,
+    stream_fn: StreamFn,
+    sleep_fn: SF,
+    reset_after: Option<usize>,
+    successes: usize,
+
+    state: State<St, SF::Sleep>,
+}
+
+impl<B, T, E, St, StreamFn> StreamRetry<B, T, E, St, StreamFn>
+where
+    B: BackoffBuilder + Clone,
+    St: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> St,
+{
+    /// Create a new retry.
+    fn new(stream_fn: StreamFn, builder: B) -> Self {
+        StreamRetry {
+            backoff: builder.clone().build(),
+            builder,
+            retryable: |_: &E| true,
+            notify: |_: &E, _: Duration| {},
+            stream_fn,
+            sleep_fn: DefaultSleeper::default(),
+            reset_after: None,
+            successes: 0,
+            state: State::Idle,
+        }
+    }
+}
+
+impl<B, T, E, St, StreamFn, SF, RF, NF> StreamRetry<B, T, E, St, StreamFn, SF, RF, NF>
+where
+    B: BackoffBuilder + Clone,
+    St: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> St,
+    SF: MaybeSleeper,
+    RF: FnMut(&E) -> bool,
+    NF: FnMut(&E, Duration),
+{
+    /// Set the sleeper for retrying.
+    ///
+    /// The sleeper should implement the [`Sleeper`] trait. The simplest way is to use a closure that returns a `Future<Output=()>`.
+    ///
+    /// If not specified, we use the [`DefaultSleeper`].
+    pub fn sleep<SN: Sleeper>(
+        self,
+        sleep_fn: SN,
+    ) -> StreamRetry<B, T, E, St, StreamFn, SN, RF, NF> {
+        StreamRetry {
+            builder: self.builder,
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            stream_fn: self.stream_fn,
+            sleep_fn,
+            reset_after: self.reset_after,
+            successes: self.successes,
+            state: State::Idle,
+        }
+    }
+
+    /// Set the conditions for retrying.
+    ///
+    /// If not specified, all errors are considered retryable.
+    pub fn when<RN: FnMut(&E) -> bool>(
+        self,
+        retryable: RN,
+    ) -> StreamRetry<B, T, E, St, StreamFn, SF, RN, NF> {
+        StreamRetry {
+            builder: self.builder,
+            backoff: self.backoff,
+            retryable,
+            notify: self.notify,
+            stream_fn: self.stream_fn,
+            sleep_fn: self.sleep_fn,
+            reset_after: self.reset_after,
+            successes: self.successes,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts.
+    ///
+    /// When a retry happens, the input function will be invoked with the error and the sleep duration before pausing.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn notify<NN: FnMut(&E, Duration)>(
+        self,
+        notify: NN,
+    ) -> StreamRetry<B, T, E, St, StreamFn, SF, RF, NN> {
+        StreamRetry {
+            builder: self.builder,
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify,
+            stream_fn: self.stream_fn,
+            sleep_fn: self.sleep_fn,
+            reset_after: self.reset_after,
+            successes: self.successes,
+            state: self.state,
+        }
+    }
+
+    /// Reset the backoff after the given number of successful items in a row.
+    ///
+    /// A long-lived stream might fail once in a while, resetting the backoff makes sure it won't be
+    /// exhausted by failures that are far apart from each other.
+    ///
+    /// If not specified, the backoff is never reset.
+    pub fn reset_after(mut self, successes: usize) -> Self {
+        self.reset_after = Some(successes.max(1));
+        self
+    }
+}
+
+/// State maintains internal state of retry.
+enum State<St, SleepFut> {
+    Idle,
+    Streaming(St),
+    Sleeping(SleepFut),
+    Done,
+}
+
+impl<B, T, E, St, StreamFn, SF, RF, NF> Stream for StreamRetry<B, T, E, St, StreamFn, SF, RF, NF>
+where
+    B: BackoffBuilder + Clone,
+    St: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> St,
+    SF: Sleeper,
+    RF: FnMut(&E) -> bool,
+    NF: FnMut(&E, Duration),
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // Safety: This is safe because we don't move the `StreamRetry` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        loop {
+            match &mut this.state {
+                State::Idle => {
+                    let st = (this.stream_fn)();
+                    this.state = State::Streaming(st);
+                    continue;
+                }
+                State::Streaming(st) => {
+                    // Safety: This is safe because we don't move the `StreamRetry` struct and this stream,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut st = unsafe { Pin::new_unchecked(st) };
+
+                    match ready!(st.as_mut().poll_next(cx)) {
+                        None => {
+                            this.state = State::Done;
+                            return Poll::Ready(None);
+                        }
+                        Some(Ok(v)) => {
+                            this.successes += 1;
+                            if this.reset_after.is_some_and(|n| this.successes >= n) {
+                                this.backoff = this.builder.clone().build();
+                                this.successes = 0;
+                            }
+                            return Poll::Ready(Some(Ok(v)));
+                        }
+                        Some(Err(err)) => {
+                            this.successes = 0;
+                            // If input error is not retryable, yield error and stop.
+                            if !(this.retryable)(&err) {
+                                this.state = State::Done;
+                                return Poll::Ready(Some(Err(err)));
+                            }
+                            match this.backoff.next() {
+                                None => {
+                                    this.state = State::Done;
+                                    return Poll::Ready(Some(Err(err)));
+                                }
+                                Some(dur) => {
+                                    (this.notify)(&err, dur);
+                                    this.state = State::Sleeping(this.sleep_fn.sleep(dur));
+                                    continue;
+                                }
+                            }
+                        }
+                    }
+                }
+                State::Sleeping(sl) => {
+                    // Safety: This is safe because we don't move the `StreamRetry` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut sl = unsafe { Pin::new_unchecked(sl) };
+
+                    ready!(sl.as_mut().poll(cx));
+                    this.state = State::Idle;
+                    continue;
+                }
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::future::ready;
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+
+    use futures_util::stream;
+    use futures_util::StreamExt;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    #[test]
+    async fn test_stream_retry() {
+        let subscribes = AtomicUsize::new(0);
+
+        let f = || {
+            let n = subscribes.fetch_add(1, Ordering::Relaxed);
+            stream::iter(vec![Ok(n), Err("disconnected")])
+        };
+
+        let mut delays = vec![];
+        let items: Vec<_> = f
+            .retry(ConstantBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .notify(|_, dur| delays.push(dur))
+            .collect()
+            .await;
+
+        assert_eq!(items, vec![Ok(0), Ok(1), Ok(2), Err("disconnected")]);
+        assert_eq!(subscribes.load(Ordering::Relaxed), 3);
+        assert_eq!(delays, vec![Duration::from_secs(1); 2]);
+    }
+
+    #[test]
+    async fn test_stream_retry_with_not_retryable_error() {
+        let f = || stream::iter(vec![Ok(1), Err("fatal"), Ok(2)]);
+
+        let items: Vec<_> = f
+            .retry(ConstantBuilder::default())
+            .sleep(|_| ready(()))
+            .when(|e| *e != "fatal")
+            .collect()
+            .await;
+
+        assert_eq!(items, vec![Ok(1), Err("fatal")]);
+    }
+
+    #[test]
+    async fn test_stream_retry_ends_with_stream() {
+        let subscribes = AtomicUsize::new(0);
+
+        let f = || {
+            let n = subscribes.fetch_add(1, Ordering::Relaxed);
+            if n == 0 {
+                stream::iter(vec![Err("disconnected")])
+            } else {
+                stream::iter(vec![Ok(n)])
+            }
+        };
+
+        let items: Vec<_> = f
+            .retry(ConstantBuilder::default())
+            .sleep(|_| ready(()))
+            .collect()
+            .await;
+
+        assert_eq!(items, vec![Ok(1)]);
+    }
+
+    #[test]
+    async fn test_stream_retry_reset_after() {
+        let subscribes = AtomicUsize::new(0);
+
+        let f = || {
+            let n = subscribes.fetch_add(1, Ordering::Relaxed);
+            if n < 5 {
+                stream::iter(vec![Ok(n), Ok(n), Err("disconnected")])
+            } else {
+                stream::iter(vec![])
+            }
+        };
+
+        let items: Vec<_> = f
+            .retry(ConstantBuilder::default().with_max_times(1))
+            .sleep(|_| ready(()))
+            .reset_after(2)
+            .collect()
+            .await;
+
+        // Every subscription yields 2 items before failing, so the backoff is never exhausted.
+        assert_eq!(items.len(), 10);
+        assert!(items.iter().all(|v| v.is_ok()));
+        assert_eq!(subscribes.load(Ordering::Relaxed), 6);
+    }
+}
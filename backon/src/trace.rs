@@ -0,0 +1,203 @@
+use core::fmt::Display;
+
+use crate::RetryStats;
+
+/// Create the span covering all attempts of a retry.
+pub(crate) fn retry_span() -> tracing::Span {
+    tracing::debug_span!("retry")
+}
+
+/// Record that an attempt failed and will be retried after `delay`.
+///
+/// The error is only recorded if the retry opted in with `trace_errors`, since requiring `Debug` or
+/// `Display` for every error would break other crates retrying such errors once any crate enables the feature.
+pub(crate) fn record_retry(
+    attempt: usize,
+    delay: core::time::Duration,
+    error: Option<&dyn Display>,
+) {
+    match error {
+        Some(error) => tracing::warn!(attempt, ?delay, %error, "attempt failed, retrying"),
+        None => tracing::warn!(attempt, ?delay, "attempt failed, retrying"),
+    }
+}
+
+/// Turn an error into the form recorded by [`record_retry`].
+pub(crate) fn display_error<E: Display>(err: &E) -> &dyn Display {
+    err
+}
+
+/// Record the final outcome of a retry.
+pub(crate) fn record_outcome<T, E>(res: &Result<T, E>, stats: &RetryStats) {
+    match res {
+        Ok(_) => tracing::debug!(
+            attempts = stats.attempts,
+            elapsed = ?stats.elapsed,
+            total_slept = ?stats.total_slept,
+            "retry succeeded"
+        ),
+        Err(_) => tracing::error!(
+            attempts = stats.attempts,
+            elapsed = ?stats.elapsed,
+            total_slept = ?stats.total_slept,
+            "retry gave up"
+        ),
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::fmt::Debug;
+    use core::time::Duration;
+    use std::sync::Mutex;
+
+    use tracing::field::Field;
+    use tracing::field::Visit;
+    use tracing::span;
+    use tracing::Event;
+    use tracing::Metadata;
+    use tracing::Subscriber;
+
+    use futures_util::FutureExt;
+
+    use crate::BlockingRetryable;
+    use crate::ConstantBuilder;
+    use crate::Retryable;
+
+    /// A subscriber recording the fields of all events.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("span={}", span.metadata().name()));
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut self.clone());
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_tracing() {
+        let recorder = Recorder::default();
+
+        let result = tracing::subscriber::with_default(recorder.clone(), || {
+            let f = || Err::<(), _>("error");
+            f.retry(
+                ConstantBuilder::default()
+                    .with_delay(Duration::from_millis(1))
+                    .with_max_times(1),
+            )
+            .sleep(|_| {})
+            .call()
+        });
+
+        assert_eq!(result, Err("error"));
+        let mut fields = recorder.0.lock().unwrap().clone();
+        // The elapsed time depends on the clock.
+        fields.retain(|field| !field.starts_with("elapsed="));
+        assert_eq!(
+            fields,
+            vec![
+                "span=retry",
+                "message=attempt failed, retrying",
+                "attempt=1",
+                "delay=1ms",
+                "message=retry gave up",
+                "attempts=2",
+                "total_slept=1ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tracing_errors() {
+        let recorder = Recorder::default();
+
+        let result = tracing::subscriber::with_default(recorder.clone(), || {
+            let f = || Err::<(), _>("error");
+            f.retry(ConstantBuilder::default().with_max_times(1))
+                .sleep(|_| {})
+                .trace_errors()
+                .call()
+        });
+
+        assert_eq!(result, Err("error"));
+        let fields = recorder.0.lock().unwrap().clone();
+        assert_eq!(
+            fields[1..5],
+            [
+                "message=attempt failed, retrying",
+                "attempt=1",
+                "delay=1s",
+                "error=error",
+            ]
+        );
+
+        let recorder = Recorder::default();
+        let result = tracing::subscriber::with_default(recorder.clone(), || {
+            let f = || async { Err::<(), _>("error") };
+            f.retry(ConstantBuilder::default().with_max_times(1))
+                .sleep(|_| core::future::ready(()))
+                .trace_errors()
+                .now_or_never()
+                .expect("retry must be ready")
+        });
+
+        assert_eq!(result, Err("error"));
+        assert!(recorder.0.lock().unwrap().contains(&"error=error".into()));
+    }
+
+    #[test]
+    fn test_tracing_without_debug() {
+        // Errors don't need to implement `Debug` while the feature is enabled.
+        struct NoDebug;
+
+        let f = || Err::<(), _>(NoDebug);
+        let result = f
+            .retry(ConstantBuilder::default().with_max_times(1))
+            .sleep(|_| {})
+            .call();
+        assert!(result.is_err());
+
+        let f = || async { Err::<(), _>(NoDebug) };
+        let result = f
+            .retry(ConstantBuilder::default().with_max_times(1))
+            .sleep(|_| core::future::ready(()))
+            .now_or_never()
+            .expect("retry must be ready");
+        assert!(result.is_err());
+    }
+}